@@ -1,10 +1,12 @@
 // Single Player Pong Game
 use web_canvas::canvas;
 use web_canvas::console;
+use web_canvas::timestep::FixedTimestep;
 
 use web_canvas::canvas::MouseButton;
 use web_canvas::canvas::KeyCode;
 
+#[derive(Clone, Copy)]
 struct Point {
     x: f32,
     y: f32,
@@ -27,19 +29,20 @@ struct PongGame {
     paddle: Paddle,
     score: u32,
     game_over: bool,
-    elapsed_time: f32,
     paused: bool,
     speed_multiplier: f32,
+    // Positions before the last update step, to interpolate drawing between steps
+    previous_ball: Point,
+    previous_paddle: Point,
 }
 
 impl PongGame {
     fn new(canvas_width: f32, canvas_height: f32) -> Self {
+        let ball_pos = Point { x: canvas_width / 2.0, y: canvas_height / 2.0 };
+        let paddle_pos = Point { x: canvas_width / 2.0 - 50.0, y: canvas_height - 30.0 };
         Self {
             ball: Ball {
-                pos: Point {
-                    x: canvas_width / 2.0,
-                    y: canvas_height / 2.0,
-                },
+                pos: ball_pos,
                 vel: Point {
                     x: 200.0,
                     y: 150.0,
@@ -47,18 +50,16 @@ impl PongGame {
                 radius: 8.0,
             },
             paddle: Paddle {
-                pos: Point {
-                    x: canvas_width / 2.0 - 50.0,
-                    y: canvas_height - 30.0,
-                },
+                pos: paddle_pos,
                 width: 100.0,
                 height: 10.0,
             },
             score: 0,
             game_over: false,
-            elapsed_time: 0.0,
             paused: true,  // Start paused - wait for Space to begin
             speed_multiplier: 1.0,
+            previous_ball: ball_pos,
+            previous_paddle: paddle_pos,
         }
     }
 
@@ -73,11 +74,26 @@ impl PongGame {
         self.game_over = false;
         self.paused = false;
         self.speed_multiplier = 1.0;
+        self.previous_ball = self.ball.pos;
+        self.previous_paddle = self.paddle.pos;
     }
 
     fn update(&mut self, canvas: &canvas::Canvas, dt: f32) {
+        self.previous_ball = self.ball.pos;
+        self.previous_paddle = self.paddle.pos;
         if self.game_over || self.paused { return; }
 
+        // Move paddle while the arrow keys are held down
+        let paddle_speed = canvas.width() * dt;
+        canvas.with_input(|input| {
+            if input.is_key_down(KeyCode::ArrowLeft) {
+                self.paddle.pos.x -= paddle_speed;
+            }
+            if input.is_key_down(KeyCode::ArrowRight) {
+                self.paddle.pos.x += paddle_speed;
+            }
+        });
+
         // Keep paddle within bounds
         if self.paddle.pos.x < 0.0 {
             self.paddle.pos.x = 0.0;
@@ -128,13 +144,16 @@ impl PongGame {
         }
     }
 
-    fn draw(&self, canvas: &canvas::Canvas) {
+    fn draw(&self, canvas: &canvas::Canvas, alpha: f32) {
         // Clear canvas
         canvas.fill_rect(0.0, 0.0, canvas.width(), canvas.height(), 0.0, canvas::BLACK);
 
         if !self.game_over {
-            canvas.fill_circle(self.ball.pos.x, self.ball.pos.y, self.ball.radius, canvas::WHITE);
-            canvas.fill_rect(self.paddle.pos.x, self.paddle.pos.y, self.paddle.width, self.paddle.height, 0.0, canvas::TAB_ORANGE);
+            // Draw between the last two update steps for smooth motion at any refresh rate
+            let ball = lerp(self.previous_ball, self.ball.pos, alpha);
+            let paddle = lerp(self.previous_paddle, self.paddle.pos, alpha);
+            canvas.fill_circle(ball.x, ball.y, self.ball.radius, canvas::WHITE);
+            canvas.fill_rect(paddle.x, paddle.y, self.paddle.width, self.paddle.height, 0.0, canvas::TAB_ORANGE);
         }
 
         // Draw score and speed multiplier
@@ -165,6 +184,10 @@ impl PongGame {
     }
 }
 
+fn lerp(from: Point, to: Point, alpha: f32) -> Point {
+    Point { x: from.x + (to.x - from.x) * alpha, y: from.y + (to.y - from.y) * alpha }
+}
+
 impl canvas::EventHandler for PongGame {
     fn on_mouse_move(&mut self, _canvas: &canvas::Canvas, x: f32, _y: f32) {
         if ! (self.paused || self.game_over) {
@@ -191,26 +214,26 @@ impl canvas::EventHandler for PongGame {
         }
     }
 
-    fn on_animation_frame(&mut self, canvas: &canvas::Canvas, elapsed: f32) {
-        self.elapsed_time = elapsed;
-        self.update(canvas, elapsed);
-        self.draw(canvas);
+    fn on_update(&mut self, canvas: &canvas::Canvas, dt: f32) {
+        self.update(canvas, dt);
+    }
+
+    fn on_render(&mut self, canvas: &canvas::Canvas, alpha: f32) {
+        self.draw(canvas, alpha);
+    }
+
+    fn on_resize(&mut self, _canvas: &canvas::Canvas, _width: f32, height: f32) {
+        // Keep the paddle at the bottom when going fullscreen and back
+        self.paddle.pos.y = height - 30.0;
+    }
+
+    fn on_blur(&mut self, _canvas: &canvas::Canvas) {
+        // Keyboard input goes elsewhere, don't let the ball run unattended
+        if !self.game_over { self.paused = true; }
     }
 
     fn on_key_down(&mut self, canvas: &canvas::Canvas, key_code: KeyCode) {
         match key_code {
-            KeyCode::ArrowLeft => { // Move paddle left
-                if ! (self.paused || self.game_over) {
-                    let speed = 2.0 * canvas.width() * self.elapsed_time;
-                    self.paddle.pos.x = f32::max(self.paddle.pos.x - speed, 0.0);
-                }
-            }
-            KeyCode::ArrowRight => { // Move paddle right
-                if ! (self.paused || self.game_over) {
-                    let speed = 2.0 * canvas.width() * self.elapsed_time;
-                    self.paddle.pos.x = f32::min(self.paddle.pos.x + speed, canvas.width() - self.paddle.width / 2.0);
-                }
-            }
             KeyCode::ArrowDown => { // Decrease speed
                 self.speed_multiplier = (self.speed_multiplier - 0.5).max(0.1);
             }
@@ -225,6 +248,13 @@ impl canvas::EventHandler for PongGame {
                 }
             }
             KeyCode::Escape => { self.speed_multiplier = 1.0; }
+            KeyCode::F => { // Toggle fullscreen
+                if canvas.is_fullscreen() {
+                    canvas.exit_fullscreen();
+                } else {
+                    canvas.request_fullscreen();
+                }
+            }
             _ => {}
         }
     }
@@ -237,8 +267,20 @@ pub fn main_function() {
     // Create pong game with standard canvas dimensions (800x400 from HTML)
     let pong_game = PongGame::new(800.0, 400.0);
 
-    let cv = canvas::Canvas::from_element("example-canvas");
+    let cv = match canvas::Canvas::from_element("example-canvas") {
+        Ok(cv) => cv,
+        Err(err) => {
+            web_canvas::error!("Cannot start Pong: {}", err);
+            return;
+        }
+    };
+    console::set_panic_hook(Some(&cv));
     cv.register_handler(pong_game);
+    cv.set_pause_when_hidden(true);
+    // Physics at a fixed 120Hz, so the ball can't tunnel through the paddle on slow frames
+    if let Err(err) = FixedTimestep::from_rate(120.0).and_then(|timestep| cv.set_fixed_timestep(Some(timestep))) {
+        web_canvas::error!("Cannot use a fixed timestep: {}", err);
+    }
     cv.start_animation_loop();
     console::log("Pong game started - move mouse to control paddle!");
 }
@@ -1,14 +1,130 @@
 #![allow(dead_code)]
 
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::canvas::Canvas;
+
 mod js {
     #[link(wasm_import_module = "Browser")]
     extern "C" {
         pub fn alert(ptr: *const u8, len: usize);
         pub fn time_now() -> f64;
         pub fn random() -> f64;
+        pub fn set_timer(id: u32, ms: u32, repeat: bool);
+        pub fn clear_timer(id: u32);
+        pub fn request_frame(id: u32);
+        pub fn cancel_frame(id: u32);
     }
 }
 
 pub fn alert(msg: &str)  { unsafe { js::alert(msg.as_ptr(), msg.len()); } }
 pub fn time_now() -> f64 { unsafe { js::time_now()                      } }
-pub fn random() -> f64   { unsafe { js::random()                        } }
\ No newline at end of file
+pub fn random() -> f64   { unsafe { js::random()                        } }
+
+/// Let the user pick files, delivered to the `on_file_dropped` callback of the canvas handler
+/// with a NaN position. `accept` filters file types as in HTML, e.g. ".csv,image/*", or is empty.
+/// Browsers only show the dialog from a user input handler.
+pub fn open_file_dialog(canvas: &Canvas, accept: &str, multiple: bool) { canvas.open_file_dialog(accept, multiple); }
+
+// Timers //////////////////////////////////////////////////////////////////////////////////
+
+/// Identifies a timeout or interval, to cancel it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u32);
+
+/// Call `callback` once after `ms` milliseconds
+pub fn set_timeout(ms: u32, callback: impl FnOnce() + 'static) -> TimerId {
+    add_timer(ms, Timer::Once(Box::new(callback)))
+}
+
+/// Call `callback` every `ms` milliseconds until the interval is cleared
+pub fn set_interval(ms: u32, callback: impl FnMut() + 'static) -> TimerId {
+    add_timer(ms, Timer::Repeat(Box::new(callback)))
+}
+
+/// Cancel a timeout that did not fire yet. Clearing a timer twice is harmless
+pub fn clear_timeout(id: TimerId) { clear_timer(id); }
+/// Stop an interval, also from inside its own callback
+pub fn clear_interval(id: TimerId) { clear_timer(id); }
+
+// Animation Frames ////////////////////////////////////////////////////////////////////////
+
+/// Identifies an animation frame request, to cancel it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameId(u32);
+
+/// Call `callback` once before the next repaint, with the frame time in milliseconds.
+/// Independent of the animation loops of canvases
+pub fn request_animation_frame(callback: impl FnOnce(f64) + 'static) -> FrameId {
+    let id = NEXT_TIMER_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+    FRAMES.with(|frames| frames.borrow_mut().insert(id, Box::new(callback)));
+    unsafe { js::request_frame(id); }
+    FrameId(id)
+}
+
+pub fn cancel_animation_frame(id: FrameId) {
+    if FRAMES.with(|frames| frames.borrow_mut().remove(&id.0)).is_some() {
+        unsafe { js::cancel_frame(id.0); }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn on_frame(id: u32, time: f64) {
+    if let Some(callback) = FRAMES.with(|frames| frames.borrow_mut().remove(&id)) {
+        callback(time);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+type FrameCallback = Box<dyn FnOnce(f64)>;
+
+enum Timer {
+    Once(Box<dyn FnOnce()>),
+    Repeat(Box<dyn FnMut()>),
+}
+
+thread_local! {
+    // Shared by timers and animation frame requests
+    static NEXT_TIMER_ID: Cell<u32> = const { Cell::new(1) };
+    static FRAMES: RefCell<HashMap<u32, FrameCallback>> = RefCell::new(HashMap::new());
+    // Callbacks by timer id. The entry stays, empty, while its callback runs, so the callback can
+    // set or clear timers, including itself
+    static TIMERS: RefCell<HashMap<u32, Option<Timer>>> = RefCell::new(HashMap::new());
+}
+
+fn add_timer(ms: u32, timer: Timer) -> TimerId {
+    let id = NEXT_TIMER_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+    let repeat = matches!(timer, Timer::Repeat(_));
+    TIMERS.with(|timers| timers.borrow_mut().insert(id, Some(timer)));
+    unsafe { js::set_timer(id, ms, repeat); }
+    TimerId(id)
+}
+
+fn clear_timer(id: TimerId) {
+    if TIMERS.with(|timers| timers.borrow_mut().remove(&id.0)).is_some() {
+        unsafe { js::clear_timer(id.0); }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn on_timer(id: u32) {
+    let timer = TIMERS.with(|timers| timers.borrow_mut().get_mut(&id).and_then(Option::take));
+    match timer {
+        Some(Timer::Once(callback)) => {
+            TIMERS.with(|timers| timers.borrow_mut().remove(&id));
+            callback();
+        }
+        Some(Timer::Repeat(mut callback)) => {
+            callback();
+            // Put the callback back, unless the interval was cleared while it ran
+            TIMERS.with(|timers| {
+                if let Some(slot) = timers.borrow_mut().get_mut(&id) {
+                    *slot = Some(Timer::Repeat(callback));
+                }
+            });
+        }
+        None => {}
+    }
+}
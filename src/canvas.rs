@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 
 use crate::console;
+use crate::error::Error;
+use crate::gamepad::{self, Gamepad};
+use crate::input::InputState;
+use crate::listener::{self, Listener, ListenerHandle};
+use crate::memory;
+use crate::timestep::{Accumulator, FixedTimestep};
 
-/// Matplotlib-inspired color palette ////////////////////////////////////////////////////
+// Matplotlib-inspired color palette ////////////////////////////////////////////////////
 
 pub type Color = (u8, u8, u8);
 
@@ -30,7 +36,7 @@ pub const TAB_GRAY: Color = (127, 127, 127);   // #7f7f7f
 pub const TAB_OLIVE: Color = (188, 189, 34);   // #bcbd22
 pub const TAB_CYAN: Color = (23, 190, 207);    // #17becf
 
-/// Event handler trait for canvas events ///////////////////////////////////////////////
+// Event handler trait for canvas events ///////////////////////////////////////////////
 
 pub trait EventHandler {
     fn on_mouse_move(&mut self, _canvas: &Canvas, _x: f32, _y: f32) {}
@@ -39,12 +45,79 @@ pub trait EventHandler {
     fn on_double_click(&mut self, _canvas: &Canvas, _x: f32, _y: f32, _button: MouseButton) {}
     fn on_wheel(&mut self, _canvas: &Canvas, _x: f32, _y: f32, _delta_y: f32) {}
     fn on_animation_frame(&mut self, _canvas: &Canvas, _elapsed: f32) {}
+    /// With a fixed timestep, replaces `on_animation_frame`: runs once per step, with the step time
+    fn on_update(&mut self, _canvas: &Canvas, _dt: f32) {}
+    /// With a fixed timestep, draws after the update steps of a frame, with `alpha` the fraction
+    /// of a step elapsed since the last one
+    fn on_render(&mut self, _canvas: &Canvas, _alpha: f32) {}
+    /// Displayed size of the canvas changed, in CSS pixels
+    fn on_resize(&mut self, _canvas: &Canvas, _width: f32, _height: f32) {}
+    /// Keyboard focus entered the canvas
+    fn on_focus(&mut self, _canvas: &Canvas) {}
+    /// Keyboard focus left the canvas. Keys and buttons still held are released
+    fn on_blur(&mut self, _canvas: &Canvas) {}
+    /// Page became visible or hidden, e.g. when switching browser tabs
+    fn on_visibility_change(&mut self, _canvas: &Canvas, _visible: bool) {}
+    /// Pointer lock was acquired or released. While locked, mouse moves only report movement deltas
+    fn on_pointer_lock_change(&mut self, _canvas: &Canvas, _locked: bool) {}
+    /// Canvas entered or left fullscreen
+    fn on_fullscreen_change(&mut self, _canvas: &Canvas, _fullscreen: bool) {}
     fn on_key_down(&mut self, _canvas: &Canvas, _key_code: KeyCode) {}
     fn on_key_up(&mut self, _canvas: &Canvas, _key_code: KeyCode) {}
+
+    /// Detailed mouse move. By default it forwards the position to `on_mouse_move`
+    fn on_mouse_move_event(&mut self, canvas: &Canvas, event: &MouseEvent) { self.on_mouse_move(canvas, event.x, event.y); }
+    /// Detailed mouse button press. By default it forwards to `on_mouse_down`
+    fn on_mouse_down_event(&mut self, canvas: &Canvas, event: &MouseEvent) { self.on_mouse_down(canvas, event.x, event.y, event.button); }
+    /// Detailed mouse button release. By default it forwards to `on_mouse_up`
+    fn on_mouse_up_event(&mut self, canvas: &Canvas, event: &MouseEvent) { self.on_mouse_up(canvas, event.x, event.y, event.button); }
+    /// Detailed double click. By default it forwards to `on_double_click`
+    fn on_double_click_event(&mut self, canvas: &Canvas, event: &MouseEvent) { self.on_double_click(canvas, event.x, event.y, event.button); }
+    /// Detailed wheel event. By default it forwards the vertical delta to `on_wheel`
+    fn on_wheel_event(&mut self, canvas: &Canvas, event: &WheelEvent) { self.on_wheel(canvas, event.x, event.y, event.delta_y); }
+    /// Mouse entered the canvas
+    fn on_mouse_enter(&mut self, _canvas: &Canvas, _event: &MouseEvent) {}
+    /// Mouse left the canvas
+    fn on_mouse_leave(&mut self, _canvas: &Canvas, _event: &MouseEvent) {}
+
+    /// Pointer went down: mouse button, pen contact or finger. Each finger is a separate pointer
+    fn on_pointer_down(&mut self, _canvas: &Canvas, _event: &PointerEvent) {}
+    /// Pointer moved, including hovering mice and pens
+    fn on_pointer_move(&mut self, _canvas: &Canvas, _event: &PointerEvent) {}
+    /// Pointer went up
+    fn on_pointer_up(&mut self, _canvas: &Canvas, _event: &PointerEvent) {}
+    /// The browser took over the pointer, e.g. for a system gesture. No up event follows
+    fn on_pointer_cancel(&mut self, _canvas: &Canvas, _event: &PointerEvent) {}
+
+    /// Gamepad connected, noticed when gamepads are polled at an animation frame
+    fn on_gamepad_connected(&mut self, _canvas: &Canvas, _gamepad: &Gamepad) {}
+    /// Gamepad with this index disconnected
+    fn on_gamepad_disconnected(&mut self, _canvas: &Canvas, _index: u32) {}
+    /// Gamepad button pressed or released. `button` is the index in the gamepad button list,
+    /// matching `gamepad::Button` for gamepads with the standard mapping
+    fn on_gamepad_button(&mut self, _canvas: &Canvas, _gamepad: &Gamepad, _button: u32, _pressed: bool) {}
+
+    /// Detailed key press. By default it forwards the logical key to `on_key_down`
+    fn on_key_down_event(&mut self, canvas: &Canvas, event: &KeyEvent) { self.on_key_down(canvas, event.key); }
+    /// Detailed key release. By default it forwards the logical key to `on_key_up`
+    fn on_key_up_event(&mut self, canvas: &Canvas, event: &KeyEvent) { self.on_key_up(canvas, event.key); }
+
+    /// Typed text, after keyboard layout and IME composition are applied
+    fn on_text_input(&mut self, _canvas: &Canvas, _text: &str) {}
+    /// Text being composed by an IME, empty once the composition ends
+    fn on_text_composition(&mut self, _canvas: &Canvas, _text: &str) {}
+
+    /// File dropped on the canvas or picked with `browser::open_file_dialog`, with its contents.
+    /// The position is where it was dropped, NaN for files from the dialog
+    fn on_file_dropped(&mut self, _canvas: &Canvas, _name: &str, _mime: &str, _data: &[u8], _x: f32, _y: f32) {}
+    /// File that could not be loaded. By default it is logged with `console::error`
+    fn on_file_error(&mut self, _canvas: &Canvas, name: &str, error: FileError) {
+        console::error(&format!("Could not load file '{}': {}", name, error));
+    }
 }
 
 /// Mouse button types
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MouseButton {
     Left = 0,
     Middle = 1,
@@ -52,9 +125,167 @@ pub enum MouseButton {
     Unknown = 255,
 }
 
-/// Keyboard key types
+/// Set of mouse buttons held down, as the `MouseEvent.buttons` bitmask
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MouseButtons(pub u32);
+
+impl MouseButtons {
+    pub fn contains(&self, button: MouseButton) -> bool {
+        let bit = match button {
+            MouseButton::Left => 1,
+            MouseButton::Right => 2,
+            MouseButton::Middle => 4,
+            MouseButton::Unknown => 0,
+        };
+        self.0 & bit != 0
+    }
+
+    pub fn is_empty(&self) -> bool { self.0 == 0 }
+}
+
+/// Mouse event details
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MouseEvent {
+    pub x: f32,
+    pub y: f32,
+    /// Movement since the previous mouse event
+    pub movement_x: f32,
+    pub movement_y: f32,
+    /// Button that changed state, `MouseButton::Unknown` for moves, enter and leave
+    pub button: MouseButton,
+    /// Buttons held down when the event happened
+    pub buttons: MouseButtons,
+    pub modifiers: Modifiers,
+}
+
+/// Wheel event details, with deltas converted to pixels whatever unit the browser used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelEvent {
+    pub x: f32,
+    pub y: f32,
+    pub delta_x: f32,
+    pub delta_y: f32,
+    pub delta_z: f32,
+    pub modifiers: Modifiers,
+    /// Trackpad pinch-zoom gesture. Browsers report them as wheel events with Ctrl held,
+    /// so Ctrl + mouse wheel is reported as a pinch as well.
+    pub is_pinch: bool,
+}
+
+/// Mouse cursor shown over the canvas
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cursor {
+    Default,
+    Pointer,
+    Crosshair,
+    Text,
+    Move,
+    Grab,
+    Grabbing,
+    NotAllowed,
+    ResizeHorizontal,
+    ResizeVertical,
+    /// Hide the cursor
+    None,
+    /// Image at the given URL, with the hotspot in pixels from its top-left corner
+    Custom(String, (u32, u32)),
+}
+
+impl Cursor {
+    /// Value of the CSS `cursor` property
+    pub fn css(&self) -> String {
+        let name = match self {
+            Cursor::Default => "default",
+            Cursor::Pointer => "pointer",
+            Cursor::Crosshair => "crosshair",
+            Cursor::Text => "text",
+            Cursor::Move => "move",
+            Cursor::Grab => "grab",
+            Cursor::Grabbing => "grabbing",
+            Cursor::NotAllowed => "not-allowed",
+            Cursor::ResizeHorizontal => "ew-resize",
+            Cursor::ResizeVertical => "ns-resize",
+            Cursor::None => "none",
+            Cursor::Custom(url, (x, y)) => return format!("url(\"{}\") {} {}, auto", url.replace('"', "%22"), x, y),
+        };
+        name.to_string()
+    }
+}
+
+/// Kind of device behind a pointer event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerType {
+    Mouse = 0,
+    Pen = 1,
+    Touch = 2,
+    Unknown = 255,
+}
+
+/// Pointer event details
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum KeyCode {
+pub struct PointerEvent {
+    /// Identifies a pointer (mouse, pen or finger) from its down event until its up or cancel event
+    pub pointer_id: i32,
+    pub pointer_type: PointerType,
+    /// First pointer of its type, e.g. the first finger to touch the screen
+    pub is_primary: bool,
+    pub x: f32,
+    pub y: f32,
+    /// Normalised pressure in [0, 1]. Hardware without pressure support reports 0.5 while pressed
+    pub pressure: f32,
+    /// Pen tilt angles in degrees, in [-90, 90]
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    /// Contact geometry size in CSS pixels
+    pub width: f32,
+    pub height: f32,
+    /// Button that changed state, `MouseButton::Unknown` for moves
+    pub button: MouseButton,
+    pub buttons: MouseButtons,
+    pub modifiers: Modifiers,
+}
+
+// Declares the KeyCode enum, its conversion from the browser key value and its names from a single list,
+// so both stay in sync. Values follow the legacy DOM `keyCode` numbering used by the TS glue.
+macro_rules! key_codes {
+    ($($name:ident = $value:literal,)*) => {
+        /// Keyboard key types
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum KeyCode {
+            $($name = $value,)*
+            Unknown = 65535,
+        }
+
+        impl From<u32> for KeyCode {
+            fn from(code: u32) -> Self {
+                match code {
+                    $($value => KeyCode::$name,)*
+                    _ => KeyCode::Unknown,
+                }
+            }
+        }
+
+        impl KeyCode {
+            /// Name of the variant, e.g. "ArrowLeft"
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(KeyCode::$name => stringify!($name),)*
+                    KeyCode::Unknown => "Unknown",
+                }
+            }
+
+            /// Key code from the name of its variant, the inverse of `name`
+            pub fn from_name(name: &str) -> Option<KeyCode> {
+                match name {
+                    $(stringify!($name) => Some(KeyCode::$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+key_codes! {
     // Numbers
     Key0 = 48,
     Key1 = 49,
@@ -109,12 +340,116 @@ pub enum KeyCode {
     Shift = 16,
     Ctrl = 17,
     Alt = 18,
+    Meta = 91,
+    CapsLock = 20,
+    ContextMenu = 93,
+
+    // Editing and navigation keys
+    Backspace = 8,
+    Delete = 46,
+    Insert = 45,
+    Home = 36,
+    End = 35,
+    PageUp = 33,
+    PageDown = 34,
+    Pause = 19,
+    PrintScreen = 44,
+    NumLock = 144,
+    ScrollLock = 145,
+
+    // Function keys
+    F1 = 112,
+    F2 = 113,
+    F3 = 114,
+    F4 = 115,
+    F5 = 116,
+    F6 = 117,
+    F7 = 118,
+    F8 = 119,
+    F9 = 120,
+    F10 = 121,
+    F11 = 122,
+    F12 = 123,
+
+    // Numpad, as physical keys (NumpadEnter reports as Enter with KeyLocation::Numpad). As logical
+    // keys they report the main keyboard key instead, e.g. Minus for "-" and Unknown for "*"
+    Numpad0 = 96,
+    Numpad1 = 97,
+    Numpad2 = 98,
+    Numpad3 = 99,
+    Numpad4 = 100,
+    Numpad5 = 101,
+    Numpad6 = 102,
+    Numpad7 = 103,
+    Numpad8 = 104,
+    Numpad9 = 105,
+    NumpadMultiply = 106,
+    NumpadAdd = 107,
+    NumpadSubtract = 109,
+    NumpadDecimal = 110,
+    NumpadDivide = 111,
 
     // Symbols
-    Minus = 189,      // -
-    Equal = 187,      // = (and + with Shift)
+    Minus = 189,        // -
+    Equal = 187,        // = (and + with Shift)
+    Comma = 188,        // ,
+    Period = 190,       // .
+    Slash = 191,        // /
+    Semicolon = 186,    // ;
+    Quote = 222,        // '
+    BracketLeft = 219,  // [
+    BracketRight = 221, // ]
+    Backslash = 220,    // \
+    Backquote = 192,    // `
+}
 
-    Unknown = 65535,
+/// Modifier keys held down while an event happened
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+/// Location of a key on the keyboard, for keys that appear more than once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyLocation {
+    Standard = 0,
+    Left = 1,
+    Right = 2,
+    Numpad = 3,
+}
+
+/// Keyboard event details
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyEvent {
+    /// Logical key produced with the current layout (`KeyboardEvent.key`)
+    pub key: KeyCode,
+    /// Physical key pressed, independent of layout and modifiers (`KeyboardEvent.code`)
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+    /// True when the event comes from the key being held down
+    pub repeat: bool,
+    pub location: KeyLocation,
+}
+
+/// Reason a dropped or picked file was not delivered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileError {
+    /// File larger than the limit set with `Canvas::set_max_file_size`, size in bytes
+    TooLarge(u64),
+    /// The browser could not read the file
+    ReadFailed,
+}
+
+impl std::fmt::Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FileError::TooLarge(size) => write!(f, "file too large ({} bytes)", size),
+            FileError::ReadFailed => write!(f, "file could not be read"),
+        }
+    }
 }
 
 /// Canvas object that encapsulates canvas operations ///////////////////////////////////
@@ -125,49 +460,232 @@ pub struct Canvas {
 }
 
 impl Canvas {
-    /// Create a Canvas handle for an existing HTML canvas element with the given name
-    pub fn from_element(name: &str) -> Canvas {
-        // Hash the canvas name to create a unique ID
-        // This is a simple hash function, the djb2 hash function
-        let mut canvas_id: u32 = 5381;
-        for b in name.bytes() {
-            canvas_id = ((canvas_id << 5).wrapping_add(canvas_id)).wrapping_add(b as u32);
-        }
-
-        // Register the canvas if it doesn't exist yet and prepare terrain at the browser
-        WASM_REGISTERED_CANVASES.with(|registered| {
-            if !registered.borrow().contains(&canvas_id) {
-                unsafe { js::register_canvas(name.as_ptr(), name.len(), canvas_id); }
-                registered.borrow_mut().push(canvas_id);
-            }
+    /// Create a Canvas handle for an existing HTML canvas element with the given name.
+    /// Handles for the same name share the same canvas
+    pub fn from_element(name: &str) -> Result<Canvas, Error> {
+        // Already registered, the lookup is released before registering at the browser
+        let registered = WASM_REGISTERED_CANVASES.with(|registered| {
+            registered.borrow().iter().find(|(n, _)| n == name).map(|(_, id)| *id)
         });
+        if let Some(canvas_id) = registered {
+            return Ok(Canvas { id: canvas_id });
+        }
 
-        // Return a new Canvas instance by value
-        Canvas { id: canvas_id }
+        // Ids come from a counter, so different names never share a canvas
+        let canvas_id = WASM_NEXT_CANVAS_ID.with(|next| next.replace(next.get() + 1));
+        match unsafe { js::register_canvas(name.as_ptr(), name.len(), canvas_id) } {
+            STATUS_OK => {}
+            STATUS_MISSING_ELEMENT => return Err(Error::MissingElement(name.to_string())),
+            STATUS_NOT_A_CANVAS => return Err(Error::NotACanvas(name.to_string())),
+            STATUS_CONTEXT_UNAVAILABLE => return Err(Error::ContextUnavailable(name.to_string())),
+            status => unreachable!("unexpected status {} registering canvas '{}'", status, name),
+        }
+        WASM_REGISTERED_CANVASES.with(|registered| registered.borrow_mut().push((name.to_string(), canvas_id)));
+        Ok(Canvas { id: canvas_id })
+    }
+
+    /// Create a new canvas element inside the element `parent_id`. The canvas is destroyed, and
+    /// its element removed, when the returned handle is dropped
+    pub fn create(parent_id: &str, width: f32, height: f32) -> Result<OwnedCanvas, Error> {
+        // The element has no id, it is registered directly under the canvas id
+        let canvas_id = WASM_NEXT_CANVAS_ID.with(|next| next.replace(next.get() + 1));
+        match unsafe { js::create_canvas(parent_id.as_ptr(), parent_id.len(), canvas_id, width, height) } {
+            STATUS_OK => Ok(OwnedCanvas { canvas: Canvas { id: canvas_id } }),
+            STATUS_MISSING_ELEMENT => Err(Error::MissingElement(parent_id.to_string())),
+            STATUS_CONTEXT_UNAVAILABLE => Err(Error::ContextUnavailable(parent_id.to_string())),
+            status => unreachable!("unexpected status {} creating a canvas in '{}'", status, parent_id),
+        }
+    }
+
+    /// Detach the canvas: remove its event listeners, stop its animation loop and forget its
+    /// handler, listeners and input state. Elements made by `create` are removed from the page,
+    /// others are left in place and can be attached again with `from_element`
+    pub fn destroy(&self) {
+        let id = self.id;
+        WASM_REGISTERED_CANVASES.with(|registered| registered.borrow_mut().retain(|(_, c)| *c != id));
+        WASM_INPUT_STATES.with(|states| states.borrow_mut().remove(&id));
+        WASM_GAMEPADS.with(|gamepads| gamepads.borrow_mut().remove(&id));
+        WASM_TIMESTEPS.with(|timesteps| timesteps.borrow_mut().remove(&id));
+        WASM_PENDING_EVENTS.with(|pending| pending.borrow_mut().retain(|(c, _)| *c != id));
+        listener::remove_all(self);
+        unsafe { js::destroy_canvas(id); }
+
+        // Dropped once the registry is released, the handler may be running or use it in its drop
+        let _handler = WASM_EVENT_HANDLERS.with(|handlers| handlers.borrow_mut().remove(&id));
     }
 
     /// Register event handler for HTML canvas
     pub fn register_handler<T: EventHandler + 'static>(&self, event_handler: T) {
         // Store event handler separately if provided
-        WASM_EVENT_HANDLERS.with(|handlers| {
-            handlers.borrow_mut().insert(self.id, Box::new(event_handler));
+        // May replace the handler currently running, which keeps running until it returns. The
+        // previous handler is dropped once the registry is released, in case its drop uses it
+        let handler: Box<dyn EventHandler> = Box::new(event_handler);
+        let _previous = WASM_EVENT_HANDLERS.with(|handlers| {
+            handlers.borrow_mut().insert(self.id, Rc::new(RefCell::new(handler)))
         });
     }
 
+    /// Closure listeners, called after the registered event handler. A canvas can have any number
+    /// of them per event, each one stays registered while its `ListenerHandle` is kept.
+    /// `on_animation_frame` listeners receive the elapsed time, also with a fixed timestep
+    pub fn on_mouse_move(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::MouseMove(Box::new(f))) }
+    pub fn on_mouse_down(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::MouseDown(Box::new(f))) }
+    pub fn on_mouse_up(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::MouseUp(Box::new(f))) }
+    pub fn on_double_click(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::DoubleClick(Box::new(f))) }
+    pub fn on_mouse_enter(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::MouseEnter(Box::new(f))) }
+    pub fn on_mouse_leave(&self, f: impl FnMut(&Canvas, &MouseEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::MouseLeave(Box::new(f))) }
+    pub fn on_wheel(&self, f: impl FnMut(&Canvas, &WheelEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::Wheel(Box::new(f))) }
+    pub fn on_pointer_down(&self, f: impl FnMut(&Canvas, &PointerEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::PointerDown(Box::new(f))) }
+    pub fn on_pointer_move(&self, f: impl FnMut(&Canvas, &PointerEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::PointerMove(Box::new(f))) }
+    pub fn on_pointer_up(&self, f: impl FnMut(&Canvas, &PointerEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::PointerUp(Box::new(f))) }
+    pub fn on_pointer_cancel(&self, f: impl FnMut(&Canvas, &PointerEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::PointerCancel(Box::new(f))) }
+    pub fn on_key_down(&self, f: impl FnMut(&Canvas, &KeyEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::KeyDown(Box::new(f))) }
+    pub fn on_key_up(&self, f: impl FnMut(&Canvas, &KeyEvent) + 'static) -> ListenerHandle { listener::add(self, Listener::KeyUp(Box::new(f))) }
+    pub fn on_text_input(&self, f: impl FnMut(&Canvas, &str) + 'static) -> ListenerHandle { listener::add(self, Listener::TextInput(Box::new(f))) }
+    pub fn on_text_composition(&self, f: impl FnMut(&Canvas, &str) + 'static) -> ListenerHandle { listener::add(self, Listener::TextComposition(Box::new(f))) }
+    pub fn on_animation_frame(&self, f: impl FnMut(&Canvas, f32) + 'static) -> ListenerHandle { listener::add(self, Listener::AnimationFrame(Box::new(f))) }
+    pub fn on_resize(&self, f: impl FnMut(&Canvas, f32, f32) + 'static) -> ListenerHandle { listener::add(self, Listener::Resize(Box::new(f))) }
+    pub fn on_focus(&self, f: impl FnMut(&Canvas) + 'static) -> ListenerHandle { listener::add(self, Listener::Focus(Box::new(f))) }
+    pub fn on_blur(&self, f: impl FnMut(&Canvas) + 'static) -> ListenerHandle { listener::add(self, Listener::Blur(Box::new(f))) }
+    pub fn on_visibility_change(&self, f: impl FnMut(&Canvas, bool) + 'static) -> ListenerHandle { listener::add(self, Listener::VisibilityChange(Box::new(f))) }
+    pub fn on_pointer_lock_change(&self, f: impl FnMut(&Canvas, bool) + 'static) -> ListenerHandle { listener::add(self, Listener::PointerLockChange(Box::new(f))) }
+    pub fn on_fullscreen_change(&self, f: impl FnMut(&Canvas, bool) + 'static) -> ListenerHandle { listener::add(self, Listener::FullscreenChange(Box::new(f))) }
+    pub fn on_file_dropped(&self, f: impl FnMut(&Canvas, &str, &str, &[u8], f32, f32) + 'static) -> ListenerHandle { listener::add(self, Listener::FileDropped(Box::new(f))) }
+    pub fn on_file_error(&self, f: impl FnMut(&Canvas, &str, FileError) + 'static) -> ListenerHandle { listener::add(self, Listener::FileError(Box::new(f))) }
+    pub fn on_gamepad_connected(&self, f: impl FnMut(&Canvas, &Gamepad) + 'static) -> ListenerHandle { listener::add(self, Listener::GamepadConnected(Box::new(f))) }
+    pub fn on_gamepad_disconnected(&self, f: impl FnMut(&Canvas, u32) + 'static) -> ListenerHandle { listener::add(self, Listener::GamepadDisconnected(Box::new(f))) }
+    pub fn on_gamepad_button(&self, f: impl FnMut(&Canvas, &Gamepad, u32, bool) + 'static) -> ListenerHandle { listener::add(self, Listener::GamepadButton(Box::new(f))) }
+
     /// Get the unique ID of this canvas
     pub fn id(&self) -> u32 { self.id }
 
+    /// Keys and mouse buttons currently held on this canvas, and those pressed or released since the last frame.
+    /// Copies the whole state, prefer `with_input` to query it every frame
+    pub fn input(&self) -> InputState { self.with_input(InputState::clone) }
+
+    /// Call `f` with the input state of this canvas without copying it
+    pub fn with_input<R>(&self, f: impl FnOnce(&InputState) -> R) -> R {
+        WASM_INPUT_STATES.with(|states| match states.borrow().get(&self.id) {
+            Some(input) => f(input),
+            None => f(&InputState::default()),
+        })
+    }
+
     /// Start the animation loop for this canvas
     pub fn start_animation_loop(&self) { unsafe { js::start_animation_loop(self.id); } }
     /// Stop the animation loop for this canvas
     pub fn stop_animation_loop(&self) { unsafe { js::stop_animation_loop(self.id); } }
 
+    /// Limit the animation loop to `fps` frames per second, skipping display frames as needed.
+    /// `None` follows the display refresh rate
+    pub fn set_target_fps(&self, fps: Option<f32>) {
+        unsafe { js::set_target_fps(self.id, fps.filter(|fps| *fps > 0.0).unwrap_or(0.0)); }
+    }
+
+    /// Only run animation frames after `request_redraw` or an input event on the canvas, so idle
+    /// canvases cost nothing. Gamepads are only polled during those frames.
+    pub fn set_redraw_on_demand(&self, on_demand: bool) { unsafe { js::set_redraw_on_demand(self.id, on_demand); } }
+    /// Run one more animation frame in on-demand mode, e.g. after the displayed data changed
+    pub fn request_redraw(&self) { unsafe { js::request_redraw(self.id); } }
+
+    /// Drive the animation loop with fixed update steps through `on_update` and `on_render`, or
+    /// go back to `on_animation_frame` with `None`. Just pressed and released input is seen by a
+    /// single update step, and kept for the next frame when a frame runs no step.
+    /// Fails, keeping the current mode, when `timestep` does not pass `FixedTimestep::check`.
+    pub fn set_fixed_timestep(&self, timestep: Option<FixedTimestep>) -> Result<(), Error> {
+        let accumulator = timestep.map(Accumulator::new).transpose()?;
+        WASM_TIMESTEPS.with(|all| match accumulator {
+            Some(accumulator) => { all.borrow_mut().insert(self.id, accumulator); }
+            None => { all.borrow_mut().remove(&self.id); }
+        });
+        Ok(())
+    }
+    /// Stop the animation loop while the page is hidden and restart it when it shows again.
+    /// Either way, the first frame after the page shows again reports a single frame as elapsed.
+    pub fn set_pause_when_hidden(&self, pause: bool) { unsafe { js::set_pause_when_hidden(self.id, pause); } }
+
+    /// Largest file, in bytes, delivered to `on_file_dropped`. Larger files are reported through
+    /// `on_file_error` without being read. Defaults to 16 MiB
+    pub fn set_max_file_size(&self, bytes: usize) { unsafe { js::set_max_file_size(self.id, bytes as f64); } }
+
+    pub(crate) fn open_file_dialog(&self, accept: &str, multiple: bool) {
+        unsafe { js::open_file_dialog(self.id, accept.as_ptr(), accept.len(), multiple); }
+    }
+
+    /// Route keyboard input through a hidden text field so IME composition works.
+    /// Typed text is always delivered through `on_text_input`, this only matters for IMEs.
+    pub fn start_text_input(&self) { unsafe { js::start_text_input(self.id); } }
+    /// Return keyboard focus to the canvas itself
+    pub fn stop_text_input(&self) { unsafe { js::stop_text_input(self.id); } }
+
+    /// Choose whether wheel events are only delivered while the canvas has keyboard focus (the default).
+    /// Without focus the page scrolls normally instead.
+    pub fn set_wheel_requires_focus(&self, requires_focus: bool) {
+        unsafe { js::set_wheel_requires_focus(self.id, requires_focus); }
+    }
+
+    /// Set the mouse cursor shown over the canvas
+    pub fn set_cursor(&self, cursor: Cursor) {
+        let css = cursor.css();
+        unsafe { js::set_cursor(self.id, css.as_ptr(), css.len()); }
+    }
+
+    /// Capture the mouse, hiding the cursor and reporting relative motion through `MouseEvent::movement_x/y`.
+    /// Browsers only grant it from a user input handler, the outcome arrives in `on_pointer_lock_change`.
+    pub fn request_pointer_lock(&self) { unsafe { js::request_pointer_lock(self.id); } }
+    /// Release the mouse if this canvas holds the pointer lock
+    pub fn exit_pointer_lock(&self) { unsafe { js::exit_pointer_lock(self.id); } }
+    pub fn is_pointer_locked(&self) -> bool { unsafe { js::is_pointer_locked(self.id) } }
+
+    /// Show the canvas fullscreen. Browsers only allow it from a user input handler.
+    /// While fullscreen, the backing store follows the screen size and `on_resize` reports it;
+    /// the previous size is restored when leaving fullscreen.
+    pub fn request_fullscreen(&self) { unsafe { js::request_fullscreen(self.id); } }
+    /// Leave fullscreen if this canvas is fullscreen
+    pub fn exit_fullscreen(&self) { unsafe { js::exit_fullscreen(self.id); } }
+    pub fn is_fullscreen(&self) -> bool { unsafe { js::is_fullscreen(self.id) } }
+
     /// Get canvas width
     pub fn width(&self) -> f32 { unsafe { js::width(self.id) } }
 
     /// Get canvas height
     pub fn height(&self) -> f32 { unsafe { js::height(self.id) } }
 
+    /// Check that the canvas still exists and can be drawn on
+    pub fn check(&self) -> Result<(), Error> {
+        match unsafe { js::status(self.id) } {
+            STATUS_OK => Ok(()),
+            STATUS_UNKNOWN_CANVAS => Err(Error::UnknownCanvas(self.id)),
+            STATUS_CONTEXT_LOST => Err(Error::ContextLost(self.id)),
+            status => unreachable!("unexpected status {} for canvas {}", status, self.id),
+        }
+    }
+
+    /// Set canvas size. In HiDPI mode this is the CSS size, and the backing store follows it
+    pub fn set_size(&self, width: f32, height: f32) {
+        unsafe {
+            js::set_width(self.id, width);
+            js::set_height(self.id, height);
+        }
+    }
+
+    /// Same as `set_size`, failing on negative or non-finite sizes and on unknown canvases
+    pub fn try_set_size(&self, width: f32, height: f32) -> Result<(), Error> {
+        if !(width.is_finite() && height.is_finite() && width >= 0.0 && height >= 0.0) {
+            return Err(Error::InvalidGeometry(format!("canvas size {}x{}", width, height)));
+        }
+        self.check()?;
+        self.set_size(width, height);
+        Ok(())
+    }
+
+    /// Size the backing store to the CSS size times `devicePixelRatio` and scale drawing to match,
+    /// so `width()`, `height()` and all drawing stay in CSS pixels but look crisp on HiDPI screens
+    pub fn set_hidpi(&self, enabled: bool) { unsafe { js::set_hidpi(self.id, enabled); } }
+
+    /// Backing store pixels per canvas unit, 1 unless HiDPI mode is enabled
+    pub fn pixel_ratio(&self) -> f32 { unsafe { js::pixel_ratio(self.id) } }
+
     /// Measures the width of a given text with a specified font.
     pub fn measure_text_width(&self, text: &str, font: &str) -> f32 {
         unsafe {
@@ -186,6 +704,14 @@ impl Canvas {
         unsafe { js::clear_rect(self.id, x, y, width, height) };
     }
 
+    /// Same as `clear_rect`, failing on non-finite coordinates, negative sizes and unusable canvases
+    pub fn try_clear_rect(&self, x: f32, y: f32, width: f32, height: f32) -> Result<(), Error> {
+        check_geometry("rectangle", &[x, y], &[width, height])?;
+        self.check()?;
+        self.clear_rect(x, y, width, height);
+        Ok(())
+    }
+
     /// Draws a filled rectangle at (x, y) with given dimensions, rotation angle and color
     pub fn fill_rect(&self, x: f32, y: f32, width: f32, height: f32, angle: f32, color: Color) {
         self.set_fill_style(color);
@@ -198,7 +724,16 @@ impl Canvas {
         }
     }
 
+    /// Same as `fill_rect`, failing on non-finite coordinates, negative sizes and unusable canvases
+    pub fn try_fill_rect(&self, x: f32, y: f32, width: f32, height: f32, angle: f32, color: Color) -> Result<(), Error> {
+        check_geometry("rectangle", &[x, y, angle], &[width, height])?;
+        self.check()?;
+        self.fill_rect(x, y, width, height, angle, color);
+        Ok(())
+    }
+
     /// Draws a stroked rectangle at (x, y) with given dimensions, rotation angle, line width and color
+    #[allow(clippy::too_many_arguments)]
     pub fn stroke_rect(&self, x: f32, y: f32, width: f32, height: f32, angle: f32, line_width: f32, color: Color) {
         self.set_stroke_style(color, line_width);
         if angle == 0.0 {
@@ -210,20 +745,53 @@ impl Canvas {
         }
     }
 
-    /// Draws a filled circle at (x, y) with given radius and color
+    /// Same as `stroke_rect`, failing on non-finite coordinates, negative sizes and unusable canvases
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_stroke_rect(&self, x: f32, y: f32, width: f32, height: f32, angle: f32, line_width: f32, color: Color) -> Result<(), Error> {
+        check_geometry("rectangle", &[x, y, angle], &[width, height, line_width])?;
+        self.check()?;
+        self.stroke_rect(x, y, width, height, angle, line_width, color);
+        Ok(())
+    }
+
+    /// Draws a filled circle at (x, y) with given radius and color. Negative radii are not drawn
     pub fn fill_circle(&self, x: f32, y: f32, radius: f32, color: Color) {
+        if let Err(err) = check_geometry("circle", &[x, y], &[radius]) {
+            console::error(&format!("fill_circle: {}", err));
+            return;
+        }
         self.set_fill_style(color);
         self.draw_circle_path(x, y, radius);
         unsafe { js::fill(self.id); }
     }
 
-    /// Draws a stroked circle at (x, y) with given radius, line width and color
+    /// Same as `fill_circle`, failing on non-finite coordinates, negative radii and unusable canvases
+    pub fn try_fill_circle(&self, x: f32, y: f32, radius: f32, color: Color) -> Result<(), Error> {
+        check_geometry("circle", &[x, y], &[radius])?;
+        self.check()?;
+        self.fill_circle(x, y, radius, color);
+        Ok(())
+    }
+
+    /// Draws a stroked circle at (x, y) with given radius, line width and color. Negative radii are not drawn
     pub fn stroke_circle(&self, x: f32, y: f32, radius: f32, line_width: f32, color: Color) {
+        if let Err(err) = check_geometry("circle", &[x, y], &[radius]) {
+            console::error(&format!("stroke_circle: {}", err));
+            return;
+        }
         self.set_stroke_style(color, line_width);
         self.draw_circle_path(x, y, radius);
         unsafe { js::stroke(self.id); }
     }
 
+    /// Same as `stroke_circle`, failing on non-finite coordinates, negative sizes and unusable canvases
+    pub fn try_stroke_circle(&self, x: f32, y: f32, radius: f32, line_width: f32, color: Color) -> Result<(), Error> {
+        check_geometry("circle", &[x, y], &[radius, line_width])?;
+        self.check()?;
+        self.stroke_circle(x, y, radius, line_width, color);
+        Ok(())
+    }
+
     /// Draws a line from (x1, y1) to (x2, y2) with given line width and color
     pub fn draw_line(&self, x1: f32, y1: f32, x2: f32, y2: f32, line_width: f32, color: Color) {
         self.set_stroke_style(color, line_width);
@@ -235,6 +803,14 @@ impl Canvas {
         }
     }
 
+    /// Same as `draw_line`, failing on non-finite coordinates, a negative line width and unusable canvases
+    pub fn try_draw_line(&self, x1: f32, y1: f32, x2: f32, y2: f32, line_width: f32, color: Color) -> Result<(), Error> {
+        check_geometry("line", &[x1, y1, x2, y2], &[line_width])?;
+        self.check()?;
+        self.draw_line(x1, y1, x2, y2, line_width, color);
+        Ok(())
+    }
+
     /// Draws text at (x, y) with given font and color
     pub fn draw_text(&self, text: &str, x: f32, y: f32, font: &str, color: Color) {
         self.set_fill_style(color);
@@ -244,6 +820,19 @@ impl Canvas {
         }
     }
 
+    /// Same as `draw_text`, failing on a non-finite position and while the font is still loading
+    /// instead of drawing with a fallback. The first call starts loading a font nothing used yet,
+    /// so calls fail with `Error::ResourceNotLoaded` until it is ready
+    pub fn try_draw_text(&self, text: &str, x: f32, y: f32, font: &str, color: Color) -> Result<(), Error> {
+        check_geometry("text", &[x, y], &[])?;
+        self.check()?;
+        if !unsafe { js::font_loaded(font.as_ptr(), font.len()) } {
+            return Err(Error::ResourceNotLoaded(font.to_string()));
+        }
+        self.draw_text(text, x, y, font, color);
+        Ok(())
+    }
+
     /// Draws a filled triangle centered at (x, y) with given size, rotation angle and color
     pub fn fill_triangle(&self, x: f32, y: f32, size: f32, angle: f32, color: Color) {
         self.set_fill_style(color);
@@ -251,6 +840,14 @@ impl Canvas {
         unsafe { js::fill(self.id); }
     }
 
+    /// Same as `fill_triangle`, failing on non-finite coordinates, a negative size and unusable canvases
+    pub fn try_fill_triangle(&self, x: f32, y: f32, size: f32, angle: f32, color: Color) -> Result<(), Error> {
+        check_geometry("triangle", &[x, y, angle], &[size])?;
+        self.check()?;
+        self.fill_triangle(x, y, size, angle, color);
+        Ok(())
+    }
+
     /// Draws a stroked triangle centered at (x, y) with given size, rotation angle, line width and color
     pub fn stroke_triangle(&self, x: f32, y: f32, size: f32, angle: f32, line_width: f32, color: Color) {
         self.set_stroke_style(color, line_width);
@@ -258,6 +855,14 @@ impl Canvas {
         unsafe { js::stroke(self.id); }
     }
 
+    /// Same as `stroke_triangle`, failing on non-finite coordinates, negative sizes and unusable canvases
+    pub fn try_stroke_triangle(&self, x: f32, y: f32, size: f32, angle: f32, line_width: f32, color: Color) -> Result<(), Error> {
+        check_geometry("triangle", &[x, y, angle], &[size, line_width])?;
+        self.check()?;
+        self.stroke_triangle(x, y, size, angle, line_width, color);
+        Ok(())
+    }
+
     /// Draws an arrow from (x1, y1) to (x2, y2) with given line width and color
     pub fn draw_arrow(&self, x1: f32, y1: f32, x2: f32, y2: f32, line_width: f32, color: Color) {
         // Skip drawing if the length is too small to be visible
@@ -274,16 +879,32 @@ impl Canvas {
         self.fill_triangle(x2 - height * angle.cos(), y2 - height * angle.sin(), height, angle, color);
     }
 
+    /// Same as `draw_arrow`, failing on non-finite coordinates, a negative line width and unusable canvases
+    pub fn try_draw_arrow(&self, x1: f32, y1: f32, x2: f32, y2: f32, line_width: f32, color: Color) -> Result<(), Error> {
+        check_geometry("arrow", &[x1, y1, x2, y2], &[line_width])?;
+        self.check()?;
+        self.draw_arrow(x1, y1, x2, y2, line_width, color);
+        Ok(())
+    }
+
     /// Draws a curve by connecting points with given line width and color
     /// x_points and y_points must have the same length
     pub fn stroke_curve(&self, x_points: &[f32], y_points: &[f32], line_width: f32, color: Color) {
+        if let Err(err) = self.try_stroke_curve(x_points, y_points, line_width, color) {
+            console::error(&format!("stroke_curve: {}", err));
+        }
+    }
+
+    /// Same as `stroke_curve`, returning why the curve could not be drawn
+    pub fn try_stroke_curve(&self, x_points: &[f32], y_points: &[f32], line_width: f32, color: Color) -> Result<(), Error> {
         if x_points.len() != y_points.len() || x_points.len() < 2 {
-            console::error("stroke_curve: x_points and y_points must have the same length and at least 2 points");
-            console::error(format!("x_points: {:?}, y_points: {:?}", x_points, y_points).as_str());
-            console::error(format!("Length: {}", x_points.len()).as_str());
-            console::error(format!("Returning without drawing curve").as_str());
-            return;
+            return Err(Error::InvalidGeometry(format!(
+                "curve needs the same number of x and y points, at least 2 (got {} and {})",
+                x_points.len(), y_points.len()
+            )));
         }
+        check_geometry("curve", &[], &[line_width])?;
+        self.check()?;
 
         self.set_stroke_style(color, line_width);
         unsafe {
@@ -296,10 +917,11 @@ impl Canvas {
 
             js::stroke(self.id);
         }
+        Ok(())
     }
 
     ///////////////////////////////////////////////////////////////////////////////////////////////
-    /// PRIVATE METHODS
+    // PRIVATE METHODS
 
     // Helper methods to reduce duplication
     fn set_fill_style(&self, color: Color) {
@@ -374,127 +996,509 @@ impl Canvas {
     }
 }
 
+/// Fail unless `coordinates` are finite and `sizes` are finite and non-negative
+fn check_geometry(shape: &str, coordinates: &[f32], sizes: &[f32]) -> Result<(), Error> {
+    if coordinates.iter().all(|c| c.is_finite()) && sizes.iter().all(|s| s.is_finite() && *s >= 0.0) {
+        return Ok(());
+    }
+    Err(Error::InvalidGeometry(format!("{} with coordinates {:?} and sizes {:?}", shape, coordinates, sizes)))
+}
+
+/// Canvas created with `Canvas::create`, destroyed when dropped. Derefs to `Canvas`
+pub struct OwnedCanvas {
+    canvas: Canvas,
+}
+
+impl OwnedCanvas {
+    /// Keep the canvas alive after the handle is gone, e.g. until `Canvas::destroy` is called
+    pub fn into_canvas(self) -> Canvas {
+        let canvas = self.canvas.clone();
+        std::mem::forget(self);
+        canvas
+    }
+}
+
+impl std::ops::Deref for OwnedCanvas {
+    type Target = Canvas;
+    fn deref(&self) -> &Canvas { &self.canvas }
+}
+
+impl Drop for OwnedCanvas {
+    fn drop(&mut self) { self.canvas.destroy(); }
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
 thread_local! {
-    static WASM_EVENT_HANDLERS: RefCell<HashMap<u32, Box<dyn EventHandler>>> = RefCell::new(HashMap::new());
+    // Handlers are shared out of the map while they run, so they can register handlers or create
+    // canvases. Events for a canvas whose handler or listeners are running further up the stack
+    // are queued in WASM_PENDING_EVENTS and delivered once they return.
+    static WASM_EVENT_HANDLERS: RefCell<HashMap<u32, SharedHandler>> = RefCell::new(HashMap::new());
+    static WASM_PENDING_EVENTS: RefCell<VecDeque<(u32, PendingEvent)>> = const { RefCell::new(VecDeque::new()) };
+    // Canvases delivering an event
+    static WASM_DISPATCHING: RefCell<Vec<u32>> = const { RefCell::new(Vec::new()) };
+    // Canvas id of each registered element name.
     // And don't expect to have too many canvases, so a vector should be fine.
-    static WASM_REGISTERED_CANVASES: RefCell<Vec<u32>> = RefCell::new(Vec::new());
+    static WASM_REGISTERED_CANVASES: RefCell<Vec<(String, u32)>> = const { RefCell::new(Vec::new()) };
+    static WASM_NEXT_CANVAS_ID: Cell<u32> = const { Cell::new(1) };
+    static WASM_INPUT_STATES: RefCell<HashMap<u32, InputState>> = RefCell::new(HashMap::new());
+    // Gamepads seen at the previous animation frame of each canvas, to report changes
+    static WASM_GAMEPADS: RefCell<HashMap<u32, Vec<Gamepad>>> = RefCell::new(HashMap::new());
+    // Fixed timestep accumulators of canvases using `on_update`/`on_render`
+    static WASM_TIMESTEPS: RefCell<HashMap<u32, Accumulator>> = RefCell::new(HashMap::new());
+}
+
+/// Record an event in the input state of `canvas_id`, before handlers see it
+fn update_input<F: FnOnce(&mut InputState)>(canvas_id: u32, f: F) {
+    WASM_INPUT_STATES.with(|states| f(states.borrow_mut().entry(canvas_id).or_default()));
+}
+
+// Status codes returned by the browser
+const STATUS_OK: u32 = 0;
+const STATUS_MISSING_ELEMENT: u32 = 1;
+const STATUS_NOT_A_CANVAS: u32 = 2;
+const STATUS_UNKNOWN_CANVAS: u32 = 3;
+const STATUS_CONTEXT_LOST: u32 = 4;
+const STATUS_CONTEXT_UNAVAILABLE: u32 = 5;
+
+type SharedHandler = Rc<RefCell<Box<dyn EventHandler>>>;
+type HandlerCall = Box<dyn FnOnce(&mut dyn EventHandler, &Canvas)>;
+type ListenerCall = Box<dyn FnMut(&mut Listener, &Canvas)>;
+type PendingEvent = (HandlerCall, ListenerCall);
+
+/// Deliver an event to `canvas_id`: `on_handler` runs with its event handler, if any, then
+/// `on_listener` with each of its closure listeners. No registry is borrowed meanwhile, and
+/// events triggered for the same canvas are delivered in order once this one is done.
+fn dispatch<H, L>(canvas_id: u32, on_handler: H, on_listener: L)
+where
+    H: FnOnce(&mut dyn EventHandler, &Canvas) + 'static,
+    L: FnMut(&mut Listener, &Canvas) + 'static,
+{
+    let event: PendingEvent = (Box::new(on_handler), Box::new(on_listener));
+    let busy = WASM_DISPATCHING.with(|dispatching| {
+        let mut dispatching = dispatching.borrow_mut();
+        let busy = dispatching.contains(&canvas_id);
+        if !busy { dispatching.push(canvas_id); }
+        busy
+    });
+    if busy {
+        WASM_PENDING_EVENTS.with(|pending| pending.borrow_mut().push_back((canvas_id, event)));
+        return;
+    }
+
+    deliver(canvas_id, event);
+    // Events queued meanwhile, for whichever handler and listeners are now registered
+    while let Some(event) = WASM_PENDING_EVENTS.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let index = pending.iter().position(|(id, _)| *id == canvas_id)?;
+        pending.remove(index).map(|(_, event)| event)
+    }) {
+        deliver(canvas_id, event);
+    }
+    WASM_DISPATCHING.with(|dispatching| dispatching.borrow_mut().retain(|id| *id != canvas_id));
+}
+
+fn deliver(canvas_id: u32, (on_handler, on_listener): PendingEvent) {
+    let canvas = Canvas { id: canvas_id };
+    // Kept alive while it runs, even if it replaces itself or destroys the canvas
+    let handler = WASM_EVENT_HANDLERS.with(|handlers| handlers.borrow().get(&canvas_id).cloned());
+    if let Some(handler) = handler {
+        on_handler(handler.borrow_mut().as_mut(), &canvas);
+    }
+    listener::emit(&canvas, on_listener);
 }
 
 /// WASM-exported mouse event handlers
 #[no_mangle]
-pub extern "C" fn on_mouse_move(canvas_id: u32, x: f32, y: f32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_mouse_move(&canvas, x, y);
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_mouse_move(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    update_input(canvas_id, |input| input.mouse_move(x, y));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_mouse_move_event(canvas, &event),
+             move |listener, canvas| if let Listener::MouseMove(f) = listener { f(canvas, &event) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_mouse_down(canvas_id: u32, x: f32, y: f32, button: u32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_mouse_down(&canvas, x, y, MouseButton::from(button));
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_mouse_down(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    update_input(canvas_id, |input| input.button_down(event.button));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_mouse_down_event(canvas, &event),
+             move |listener, canvas| if let Listener::MouseDown(f) = listener { f(canvas, &event) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_mouse_up(canvas_id: u32, x: f32, y: f32, button: u32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_mouse_up(&canvas, x, y, MouseButton::from(button));
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_mouse_up(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    update_input(canvas_id, |input| input.button_up(event.button));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_mouse_up_event(canvas, &event),
+             move |listener, canvas| if let Listener::MouseUp(f) = listener { f(canvas, &event) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_double_click(canvas_id: u32, x: f32, y: f32, button: u32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_double_click(&canvas, x, y, MouseButton::from(button));
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_double_click(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_double_click_event(canvas, &event),
+             move |listener, canvas| if let Listener::DoubleClick(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_mouse_enter(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_mouse_enter(canvas, &event),
+             move |listener, canvas| if let Listener::MouseEnter(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_mouse_leave(canvas_id: u32, x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = mouse_event(x, y, movement_x, movement_y, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_mouse_leave(canvas, &event),
+             move |listener, canvas| if let Listener::MouseLeave(f) = listener { f(canvas, &event) });
+}
+
+/// WASM-exported pointer event handlers
+#[no_mangle]
+pub extern "C" fn on_pointer_down(canvas_id: u32, pointer_id: i32, pointer_type: u32, is_primary: u32, x: f32, y: f32, pressure: f32,
+                                  tilt_x: f32, tilt_y: f32, width: f32, height: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = pointer_event(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_pointer_down(canvas, &event),
+             move |listener, canvas| if let Listener::PointerDown(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_pointer_move(canvas_id: u32, pointer_id: i32, pointer_type: u32, is_primary: u32, x: f32, y: f32, pressure: f32,
+                                  tilt_x: f32, tilt_y: f32, width: f32, height: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = pointer_event(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_pointer_move(canvas, &event),
+             move |listener, canvas| if let Listener::PointerMove(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_pointer_up(canvas_id: u32, pointer_id: i32, pointer_type: u32, is_primary: u32, x: f32, y: f32, pressure: f32,
+                                tilt_x: f32, tilt_y: f32, width: f32, height: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = pointer_event(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_pointer_up(canvas, &event),
+             move |listener, canvas| if let Listener::PointerUp(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_pointer_cancel(canvas_id: u32, pointer_id: i32, pointer_type: u32, is_primary: u32, x: f32, y: f32, pressure: f32,
+                                    tilt_x: f32, tilt_y: f32, width: f32, height: f32, button: u32, buttons: u32, modifiers: u32) {
+    let event = pointer_event(pointer_id, pointer_type, is_primary, x, y, pressure, tilt_x, tilt_y, width, height, button, buttons, modifiers);
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_pointer_cancel(canvas, &event),
+             move |listener, canvas| if let Listener::PointerCancel(f) = listener { f(canvas, &event) });
 }
 
 #[no_mangle]
 pub extern "C" fn on_animation_frame(canvas_id: u32, elapsed: f32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_animation_frame(&canvas, elapsed);
-            handlers_ref.insert(canvas_id, handler);
-        }
+    poll_gamepads(canvas_id);
+
+    let steps = WASM_TIMESTEPS.with(|all| {
+        all.borrow_mut().get_mut(&canvas_id).map(|acc| (acc.advance(elapsed), acc.timestep().step))
     });
+    let Some((steps, step)) = steps else {
+        dispatch(canvas_id,
+                 move |handler, canvas| handler.on_animation_frame(canvas, elapsed),
+                 move |listener, canvas| if let Listener::AnimationFrame(f) = listener { f(canvas, elapsed) });
+        update_input(canvas_id, |input| input.end_frame());
+        return;
+    };
+
+    for _ in 0..steps {
+        dispatch(canvas_id, move |handler, canvas| handler.on_update(canvas, step), |_, _| {});
+        update_input(canvas_id, |input| input.end_frame());
+    }
+    let alpha = WASM_TIMESTEPS.with(|all| all.borrow().get(&canvas_id).map_or(0.0, |acc| acc.alpha()));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_render(canvas, alpha),
+             move |listener, canvas| if let Listener::AnimationFrame(f) = listener { f(canvas, elapsed) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_key_down(canvas_id: u32, key_code: u32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_key_down(&canvas, KeyCode::from(key_code));
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_key_down(canvas_id: u32, key: u32, code: u32, modifiers: u32, repeat: u32, location: u32) {
+    let event = key_event(key, code, modifiers, repeat, location);
+    update_input(canvas_id, |input| input.key_down(event.code));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_key_down_event(canvas, &event),
+             move |listener, canvas| if let Listener::KeyDown(f) = listener { f(canvas, &event) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_key_up(canvas_id: u32, key_code: u32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_key_up(&canvas, KeyCode::from(key_code));
-            handlers_ref.insert(canvas_id, handler);
-        }
-    });
+pub extern "C" fn on_key_up(canvas_id: u32, key: u32, code: u32, modifiers: u32, repeat: u32, location: u32) {
+    let event = key_event(key, code, modifiers, repeat, location);
+    update_input(canvas_id, |input| input.key_up(event.code));
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_key_up_event(canvas, &event),
+             move |listener, canvas| if let Listener::KeyUp(f) = listener { f(canvas, &event) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_resize(canvas_id: u32, width: f32, height: f32) {
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_resize(canvas, width, height),
+             move |listener, canvas| if let Listener::Resize(f) = listener { f(canvas, width, height) });
 }
 
 #[no_mangle]
-pub extern "C" fn on_wheel(canvas_id: u32, x: f32, y: f32, delta_y: f32) {
-    WASM_EVENT_HANDLERS.with(|handlers| {
-        let mut handlers_ref = handlers.borrow_mut();
-        if let Some(mut handler) = handlers_ref.remove(&canvas_id) {
-            let canvas = Canvas { id: canvas_id };
-            handler.on_wheel(&canvas, x, y, delta_y);
-            handlers_ref.insert(canvas_id, handler);
+pub extern "C" fn on_focus(canvas_id: u32) {
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_focus(canvas),
+             move |listener, canvas| if let Listener::Focus(f) = listener { f(canvas) });
+}
+
+/// Focus left the canvas, so key and button releases will not be reported anymore
+#[no_mangle]
+pub extern "C" fn on_blur(canvas_id: u32) {
+    update_input(canvas_id, |input| input.release_all());
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_blur(canvas),
+             move |listener, canvas| if let Listener::Blur(f) = listener { f(canvas) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_pointer_lock_change(canvas_id: u32, locked: u32) {
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_pointer_lock_change(canvas, locked != 0),
+             move |listener, canvas| if let Listener::PointerLockChange(f) = listener { f(canvas, locked != 0) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_fullscreen_change(canvas_id: u32, fullscreen: u32) {
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_fullscreen_change(canvas, fullscreen != 0),
+             move |listener, canvas| if let Listener::FullscreenChange(f) = listener { f(canvas, fullscreen != 0) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_visibility_change(canvas_id: u32, visible: u32) {
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_visibility_change(canvas, visible != 0),
+             move |listener, canvas| if let Listener::VisibilityChange(f) = listener { f(canvas, visible != 0) });
+}
+
+#[no_mangle]
+pub extern "C" fn on_wheel(canvas_id: u32, x: f32, y: f32, delta_x: f32, delta_y: f32, delta_z: f32, modifiers: u32, is_pinch: u32) {
+    let event = WheelEvent {
+        x,
+        y,
+        delta_x,
+        delta_y,
+        delta_z,
+        modifiers: Modifiers::from(modifiers),
+        is_pinch: is_pinch != 0,
+    };
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_wheel_event(canvas, &event),
+             move |listener, canvas| if let Listener::Wheel(f) = listener { f(canvas, &event) });
+}
+
+/// Typed text, in a buffer the browser filled through `wasm_alloc`
+///
+/// # Safety
+/// `text_ptr` and `text_len` must come from a single `wasm_alloc` call, ownership of the buffer passes to this function.
+#[no_mangle]
+pub unsafe extern "C" fn on_text_input(canvas_id: u32, text_ptr: *mut u8, text_len: usize) {
+    let text = memory::take_string(text_ptr, text_len);
+    let handler_text = text.clone();
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_text_input(canvas, &handler_text),
+             move |listener, canvas| if let Listener::TextInput(f) = listener { f(canvas, &text) });
+}
+
+/// Text being composed by an IME
+///
+/// # Safety
+/// Same requirements as `on_text_input`.
+#[no_mangle]
+pub unsafe extern "C" fn on_text_composition(canvas_id: u32, text_ptr: *mut u8, text_len: usize) {
+    let text = memory::take_string(text_ptr, text_len);
+    let handler_text = text.clone();
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_text_composition(canvas, &handler_text),
+             move |listener, canvas| if let Listener::TextComposition(f) = listener { f(canvas, &text) });
+}
+
+/// File contents, with its name and MIME type, in buffers the browser filled through `wasm_alloc`
+///
+/// # Safety
+/// Each pointer and length pair must come from its own `wasm_alloc` call, ownership of the buffers passes to this function.
+#[no_mangle]
+pub unsafe extern "C" fn on_file_dropped(canvas_id: u32, name_ptr: *mut u8, name_len: usize, mime_ptr: *mut u8, mime_len: usize,
+                                         data_ptr: *mut u8, data_len: usize, x: f32, y: f32) {
+    let name = memory::take_string(name_ptr, name_len);
+    let mime = memory::take_string(mime_ptr, mime_len);
+    // Shared rather than copied, files can be large
+    let file = Rc::new((name, mime, memory::take_bytes(data_ptr, data_len)));
+    let handler_file = file.clone();
+    dispatch(canvas_id,
+             move |handler, canvas| {
+                 let (name, mime, data) = &*handler_file;
+                 handler.on_file_dropped(canvas, name, mime, data, x, y)
+             },
+             move |listener, canvas| if let Listener::FileDropped(f) = listener {
+                 let (name, mime, data) = &*file;
+                 f(canvas, name, mime, data, x, y)
+             });
+}
+
+/// File that could not be loaded, with its name in a buffer the browser filled through `wasm_alloc`
+///
+/// # Safety
+/// Same requirements as `on_text_input`.
+#[no_mangle]
+pub unsafe extern "C" fn on_file_error(canvas_id: u32, name_ptr: *mut u8, name_len: usize, error: u32, size: f64) {
+    let name = memory::take_string(name_ptr, name_len);
+    let error = match error {
+        1 => FileError::TooLarge(size as u64),
+        _ => FileError::ReadFailed,
+    };
+    let handler_name = name.clone();
+    dispatch(canvas_id,
+             move |handler, canvas| handler.on_file_error(canvas, &handler_name, error),
+             move |listener, canvas| if let Listener::FileError(f) = listener { f(canvas, &name, error) });
+}
+
+/// Read the gamepads and report what changed since the previous frame of this canvas
+fn poll_gamepads(canvas_id: u32) {
+    let gamepads = gamepad::gamepads();
+    let previous = WASM_GAMEPADS.with(|all| all.borrow_mut().insert(canvas_id, gamepads.clone()).unwrap_or_default());
+    update_input(canvas_id, |input| input.update_gamepads(&gamepads));
+
+    for old in previous.iter().filter(|old| !gamepads.iter().any(|pad| pad.index == old.index)) {
+        let index = old.index;
+        dispatch(canvas_id,
+                 move |handler, canvas| handler.on_gamepad_disconnected(canvas, index),
+                 move |listener, canvas| if let Listener::GamepadDisconnected(f) = listener { f(canvas, index) });
+    }
+    for pad in &gamepads {
+        let Some(old) = previous.iter().find(|old| old.index == pad.index) else {
+            let pad = Rc::new(pad.clone());
+            let handler_pad = pad.clone();
+            dispatch(canvas_id,
+                     move |handler, canvas| handler.on_gamepad_connected(canvas, &handler_pad),
+                     move |listener, canvas| if let Listener::GamepadConnected(f) = listener { f(canvas, &pad) });
+            continue;
+        };
+        for (button, &pressed) in pad.pressed.iter().enumerate() {
+            if old.pressed.get(button).copied().unwrap_or(false) != pressed {
+                let pad = Rc::new(pad.clone());
+                let handler_pad = pad.clone();
+                dispatch(canvas_id,
+                         move |handler, canvas| handler.on_gamepad_button(canvas, &handler_pad, button as u32, pressed),
+                         move |listener, canvas| if let Listener::GamepadButton(f) = listener { f(canvas, &pad, button as u32, pressed) });
+            }
         }
-    });
+    }
+}
+
+fn mouse_event(x: f32, y: f32, movement_x: f32, movement_y: f32, button: u32, buttons: u32, modifiers: u32) -> MouseEvent {
+    MouseEvent {
+        x,
+        y,
+        movement_x,
+        movement_y,
+        button: MouseButton::from(button),
+        buttons: MouseButtons(buttons),
+        modifiers: Modifiers::from(modifiers),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pointer_event(pointer_id: i32, pointer_type: u32, is_primary: u32, x: f32, y: f32, pressure: f32,
+                 tilt_x: f32, tilt_y: f32, width: f32, height: f32, button: u32, buttons: u32, modifiers: u32) -> PointerEvent {
+    PointerEvent {
+        pointer_id,
+        pointer_type: PointerType::from(pointer_type),
+        is_primary: is_primary != 0,
+        x,
+        y,
+        pressure,
+        tilt_x,
+        tilt_y,
+        width,
+        height,
+        button: MouseButton::from(button),
+        buttons: MouseButtons(buttons),
+        modifiers: Modifiers::from(modifiers),
+    }
+}
+
+fn key_event(key: u32, code: u32, modifiers: u32, repeat: u32, location: u32) -> KeyEvent {
+    KeyEvent {
+        key: KeyCode::from(key),
+        code: KeyCode::from(code),
+        modifiers: Modifiers::from(modifiers),
+        repeat: repeat != 0,
+        location: KeyLocation::from(location),
+    }
+}
+
+// Declares the browser imports of a JS module. Other targets get stubs returning defaults, e.g.
+// `STATUS_OK` for `status`, so native tests link and run without a browser.
+macro_rules! browser_imports {
+    ($module:literal { $(pub fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?;)* }) => {
+        #[cfg(target_arch = "wasm32")]
+        #[link(wasm_import_module = $module)]
+        extern "C" {
+            $(pub fn $name($($arg: $ty),*) $(-> $ret)?;)*
+        }
+
+        $(
+            #[cfg(not(target_arch = "wasm32"))]
+            #[allow(unused_variables)]
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? { Default::default() }
+        )*
+    };
 }
 
 /// API imported from JavaScript at the browser //////////////////////////////////////////
 mod js {
-    #[link(wasm_import_module = "Canvas")]
-    extern "C" {
-        pub fn register_canvas(name_ptr: *const u8, name_len: usize, canvas_id: u32);
+    browser_imports! { "Canvas" {
+        pub fn register_canvas(name_ptr: *const u8, name_len: usize, canvas_id: u32) -> u32;
+        pub fn create_canvas(parent_ptr: *const u8, parent_len: usize, canvas_id: u32, width: f32, height: f32) -> u32;
+        pub fn destroy_canvas(canvas_id: u32);
+        pub fn status(canvas_id: u32) -> u32;
+        pub fn font_loaded(font_ptr: *const u8, font_len: usize) -> bool;
         pub fn start_animation_loop(canvas_id: u32);
         pub fn stop_animation_loop(canvas_id: u32);
+        pub fn set_pause_when_hidden(canvas_id: u32, pause: bool);
+        pub fn set_target_fps(canvas_id: u32, fps: f32);
+        pub fn set_redraw_on_demand(canvas_id: u32, on_demand: bool);
+        pub fn request_redraw(canvas_id: u32);
+        pub fn set_max_file_size(canvas_id: u32, bytes: f64);
+        pub fn open_file_dialog(canvas_id: u32, accept_ptr: *const u8, accept_len: usize, multiple: bool);
+        pub fn start_text_input(canvas_id: u32);
+        pub fn stop_text_input(canvas_id: u32);
+        pub fn set_wheel_requires_focus(canvas_id: u32, requires_focus: bool);
+        pub fn set_cursor(canvas_id: u32, css_ptr: *const u8, css_len: usize);
+        pub fn request_pointer_lock(canvas_id: u32);
+        pub fn exit_pointer_lock(canvas_id: u32);
+        pub fn is_pointer_locked(canvas_id: u32) -> bool;
+        pub fn request_fullscreen(canvas_id: u32);
+        pub fn exit_fullscreen(canvas_id: u32);
+        pub fn is_fullscreen(canvas_id: u32) -> bool;
         pub fn arc(canvas_id: u32, x: f32, y: f32, radius: f32, start_angle: f32, end_angle: f32);
         pub fn begin_path(canvas_id: u32);
         pub fn clear_rect(canvas_id: u32, x: f32, y: f32, width: f32, height: f32);
         pub fn fill(canvas_id: u32);
         pub fn fill_rect(canvas_id: u32, x: f32, y: f32, width: f32, height: f32);
         pub fn height(canvas_id: u32) -> f32;
+        pub fn set_height(canvas_id: u32, height: f32);
+        pub fn set_width(canvas_id: u32, width: f32);
+        pub fn set_hidpi(canvas_id: u32, enabled: bool);
+        pub fn pixel_ratio(canvas_id: u32) -> f32;
         pub fn line_to(canvas_id: u32, x: f32, y: f32);
         pub fn move_to(canvas_id: u32, x: f32, y: f32);
         pub fn set_fill_color(canvas_id: u32, r: u8, g: u8, b: u8, a: f32);
@@ -506,6 +1510,28 @@ mod js {
         pub fn fill_text(canvas_id: u32, text_ptr: *const u8, text_len: usize, x: f32, y: f32);
         pub fn set_font(canvas_id: u32, font_ptr: *const u8, font_len: usize);
         pub fn measure_text_width(canvas_id: u32, text_ptr: *const u8, text_len: usize) -> f32;
+    }}
+}
+
+impl MouseButton {
+    /// Name of the variant, e.g. "Left"
+    pub fn name(&self) -> &'static str {
+        match self {
+            MouseButton::Left => "Left",
+            MouseButton::Middle => "Middle",
+            MouseButton::Right => "Right",
+            MouseButton::Unknown => "Unknown",
+        }
+    }
+
+    /// Mouse button from the name of its variant, the inverse of `name`
+    pub fn from_name(name: &str) -> Option<MouseButton> {
+        match name {
+            "Left" => Some(MouseButton::Left),
+            "Middle" => Some(MouseButton::Middle),
+            "Right" => Some(MouseButton::Right),
+            _ => None,
+        }
     }
 }
 
@@ -520,59 +1546,225 @@ impl From<u32> for MouseButton {
     }
 }
 
-impl From<u32> for KeyCode {
-    fn from(code: u32) -> Self {
-        match code {
-            48 => KeyCode::Key0,
-            49 => KeyCode::Key1,
-            50 => KeyCode::Key2,
-            51 => KeyCode::Key3,
-            52 => KeyCode::Key4,
-            53 => KeyCode::Key5,
-            54 => KeyCode::Key6,
-            55 => KeyCode::Key7,
-            56 => KeyCode::Key8,
-            57 => KeyCode::Key9,
-            65 => KeyCode::A,
-            66 => KeyCode::B,
-            67 => KeyCode::C,
-            68 => KeyCode::D,
-            69 => KeyCode::E,
-            70 => KeyCode::F,
-            71 => KeyCode::G,
-            72 => KeyCode::H,
-            73 => KeyCode::I,
-            74 => KeyCode::J,
-            75 => KeyCode::K,
-            76 => KeyCode::L,
-            77 => KeyCode::M,
-            78 => KeyCode::N,
-            79 => KeyCode::O,
-            80 => KeyCode::P,
-            81 => KeyCode::Q,
-            82 => KeyCode::R,
-            83 => KeyCode::S,
-            84 => KeyCode::T,
-            85 => KeyCode::U,
-            86 => KeyCode::V,
-            87 => KeyCode::W,
-            88 => KeyCode::X,
-            89 => KeyCode::Y,
-            90 => KeyCode::Z,
-            37 => KeyCode::ArrowLeft,
-            38 => KeyCode::ArrowUp,
-            39 => KeyCode::ArrowRight,
-            40 => KeyCode::ArrowDown,
-            32 => KeyCode::Space,
-            13 => KeyCode::Enter,
-            27 => KeyCode::Escape,
-            9 => KeyCode::Tab,
-            16 => KeyCode::Shift,
-            17 => KeyCode::Ctrl,
-            18 => KeyCode::Alt,
-            189 => KeyCode::Minus,
-            187 => KeyCode::Equal,
-            _ => KeyCode::Unknown,
-        }
-    }
-}
\ No newline at end of file
+impl From<u32> for PointerType {
+    fn from(pointer_type: u32) -> Self {
+        match pointer_type {
+            0 => PointerType::Mouse,
+            1 => PointerType::Pen,
+            2 => PointerType::Touch,
+            _ => PointerType::Unknown,
+        }
+    }
+}
+
+impl From<u32> for Modifiers {
+    fn from(bits: u32) -> Self {
+        Modifiers {
+            shift: bits & 1 != 0,
+            ctrl: bits & 2 != 0,
+            alt: bits & 4 != 0,
+            meta: bits & 8 != 0,
+        }
+    }
+}
+
+impl From<u32> for KeyLocation {
+    fn from(location: u32) -> Self {
+        match location {
+            1 => KeyLocation::Left,
+            2 => KeyLocation::Right,
+            3 => KeyLocation::Numpad,
+            _ => KeyLocation::Standard,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+    type Action = Box<dyn FnOnce(&Canvas)>;
+
+    // Handler recording its events, running `action` during its first focus event
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        action: Option<Action>,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, log: &Log, action: impl FnOnce(&Canvas) + 'static) -> Recorder {
+            Recorder { name, log: log.clone(), action: Some(Box::new(action)) }
+        }
+    }
+
+    impl EventHandler for Recorder {
+        fn on_focus(&mut self, canvas: &Canvas) {
+            self.log.borrow_mut().push(format!("{} focus {}", self.name, canvas.id()));
+            if let Some(action) = self.action.take() {
+                action(canvas);
+            }
+            self.log.borrow_mut().push(format!("{} focus {} end", self.name, canvas.id()));
+        }
+
+        fn on_blur(&mut self, canvas: &Canvas) {
+            self.log.borrow_mut().push(format!("{} blur {}", self.name, canvas.id()));
+        }
+
+        fn on_resize(&mut self, canvas: &Canvas, width: f32, height: f32) {
+            self.log.borrow_mut().push(format!("{} resize {} {}x{}", self.name, canvas.id(), width, height));
+        }
+    }
+
+    impl Drop for Recorder {
+        fn drop(&mut self) { self.log.borrow_mut().push(format!("{} dropped", self.name)); }
+    }
+
+    fn listen(canvas: &Canvas, log: &Log) {
+        let focus_log = log.clone();
+        canvas.on_focus(move |canvas| focus_log.borrow_mut().push(format!("listener focus {}", canvas.id()))).forget();
+        let blur_log = log.clone();
+        canvas.on_blur(move |canvas| blur_log.borrow_mut().push(format!("listener blur {}", canvas.id()))).forget();
+    }
+
+    fn entries(log: &Log) -> Vec<String> { log.borrow().clone() }
+
+    // Values of the KEY_TABLE in the TS glue, explicit entries and those pushed by its loops
+    fn ts_key_values() -> Vec<u32> {
+        let source = include_str!("../ts/canvas-wasm.ts");
+        let start = source.find("const KEY_TABLE").expect("KEY_TABLE in the TS glue");
+        let end = start + source[start..].find("const KEYS_BY_CODE").expect("end of KEY_TABLE");
+        let number = |text: &str| text.trim().parse::<u32>().unwrap_or_else(|_| panic!("number in KEY_TABLE: {:?}", text));
+
+        let mut values = Vec::new();
+        let mut range = 0..0;
+        for line in source[start..end].lines().map(str::trim) {
+            if let Some(entry) = line.strip_prefix('[').filter(|_| line.ends_with("]],")) {
+                values.push(number(entry.split(',').next().unwrap()));
+            } else if let Some(bounds) = line.strip_prefix("for (let i = ") {
+                // for (let i = A; i < B; i++) or i <= B
+                let (first, rest) = bounds.split_once("; i <").unwrap();
+                let (inclusive, last) = match rest.strip_prefix('=') { Some(rest) => (1, rest), None => (0, rest) };
+                range = number(first)..number(last.split(';').next().unwrap()) + inclusive;
+            } else if let Some(push) = line.strip_prefix("KEY_TABLE.push([") {
+                let base = number(push.split(" + i").next().unwrap());
+                values.extend(range.clone().map(|i| base + i));
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn key_table_matches_key_codes() {
+        let ts_values = ts_key_values();
+        for value in &ts_values {
+            let key = KeyCode::from(*value);
+            assert_ne!(key, KeyCode::Unknown, "TS key value {} has no KeyCode", value);
+            assert_eq!(key as u32, *value);
+        }
+        for value in 0..=u16::MAX as u32 {
+            let key = KeyCode::from(value);
+            if key != KeyCode::Unknown {
+                assert!(ts_values.contains(&value), "KeyCode::{} ({}) is missing from the TS KEY_TABLE", key.name(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn geometry_must_be_finite_with_non_negative_sizes() {
+        assert_eq!(check_geometry("circle", &[-5.0, 3.0], &[0.0]), Ok(()));
+        assert!(matches!(check_geometry("circle", &[0.0, 0.0], &[-1.0]), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(check_geometry("circle", &[f32::NAN, 0.0], &[1.0]), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(check_geometry("line", &[0.0, f32::INFINITY], &[]), Err(Error::InvalidGeometry(_))));
+        assert!(matches!(check_geometry("rectangle", &[0.0, 0.0], &[1.0, f32::INFINITY]), Err(Error::InvalidGeometry(_))));
+    }
+
+    #[test]
+    fn handler_replaces_itself() {
+        let log = Log::default();
+        let canvas = Canvas { id: 101 };
+        let replacement_log = log.clone();
+        canvas.register_handler(Recorder::new("a", &log, move |canvas| {
+            canvas.register_handler(Recorder::new("b", &replacement_log, |_| {}));
+        }));
+
+        on_focus(101);
+        // The replaced handler finishes its event and is dropped once it returns
+        assert_eq!(entries(&log), ["a focus 101", "a focus 101 end", "a dropped"]);
+
+        on_blur(101);
+        assert_eq!(entries(&log)[3..], ["b blur 101"]);
+    }
+
+    #[test]
+    fn handler_registers_handler_for_other_canvas() {
+        let log = Log::default();
+        let other_log = log.clone();
+        Canvas { id: 102 }.register_handler(Recorder::new("a", &log, move |_| {
+            Canvas { id: 103 }.register_handler(Recorder::new("b", &other_log, |_| {}));
+            // Events for another canvas are not held back
+            on_blur(103);
+        }));
+
+        on_focus(102);
+        assert_eq!(entries(&log), ["a focus 102", "b blur 103", "a focus 102 end"]);
+
+        on_blur(102);
+        on_resize(103, 1.0, 2.0);
+        assert_eq!(entries(&log)[3..], ["a blur 102", "b resize 103 1x2"]);
+    }
+
+    #[test]
+    fn events_for_own_canvas_are_queued_in_order() {
+        let log = Log::default();
+        let canvas = Canvas { id: 104 };
+        canvas.register_handler(Recorder::new("a", &log, |canvas| {
+            on_blur(canvas.id());
+            on_resize(canvas.id(), 3.0, 4.0);
+        }));
+        listen(&canvas, &log);
+
+        on_focus(104);
+        // Listeners see the focus event before the events triggered by the handler
+        assert_eq!(entries(&log), [
+            "a focus 104", "a focus 104 end", "listener focus 104",
+            "a blur 104", "listener blur 104",
+            "a resize 104 3x4",
+        ]);
+        assert!(WASM_PENDING_EVENTS.with(|pending| pending.borrow().is_empty()));
+    }
+
+    #[test]
+    fn events_triggered_by_listeners_are_queued() {
+        let log = Log::default();
+        let canvas = Canvas { id: 105 };
+        canvas.register_handler(Recorder::new("a", &log, |_| {}));
+        canvas.on_focus(|canvas| on_blur(canvas.id())).forget();
+        listen(&canvas, &log);
+
+        on_focus(105);
+        assert_eq!(entries(&log), ["a focus 105", "a focus 105 end", "listener focus 105", "a blur 105", "listener blur 105"]);
+    }
+
+    #[test]
+    fn destroy_from_handler() {
+        let log = Log::default();
+        let canvas = Canvas { id: 106 };
+        canvas.register_handler(Recorder::new("a", &log, |canvas| {
+            on_blur(canvas.id());
+            canvas.destroy();
+        }));
+        listen(&canvas, &log);
+
+        on_focus(106);
+        // Neither the queued blur event nor the removed listeners run, and the handler is dropped
+        // once it returns
+        assert_eq!(entries(&log), ["a focus 106", "a focus 106 end", "a dropped"]);
+        assert!(WASM_PENDING_EVENTS.with(|pending| pending.borrow().is_empty()));
+        assert!(WASM_DISPATCHING.with(|dispatching| dispatching.borrow().is_empty()));
+
+        on_focus(106);
+        assert_eq!(entries(&log).len(), 3);
+    }
+}
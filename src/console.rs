@@ -1,11 +1,85 @@
 #![allow(dead_code)]
+use crate::canvas::{self, Canvas};
+
 mod js {
     #[link(wasm_import_module = "Console")]
     extern "C" {
         pub fn log(ptr: *const u8, len: usize);
         pub fn error(ptr: *const u8, len: usize);
+        pub fn warn(ptr: *const u8, len: usize);
+        pub fn info(ptr: *const u8, len: usize);
+        pub fn debug(ptr: *const u8, len: usize);
     }
 }
 
 pub fn log(msg: &str) { unsafe { js::log(msg.as_ptr(), msg.len()); } }
-pub fn error(msg: &str) { unsafe { js::error(msg.as_ptr(), msg.len()); } }
\ No newline at end of file
+pub fn error(msg: &str) { unsafe { js::error(msg.as_ptr(), msg.len()); } }
+pub fn warn(msg: &str) { unsafe { js::warn(msg.as_ptr(), msg.len()); } }
+pub fn info(msg: &str) { unsafe { js::info(msg.as_ptr(), msg.len()); } }
+pub fn debug(msg: &str) { unsafe { js::debug(msg.as_ptr(), msg.len()); } }
+
+/// Report panics through `console::error` with their message and location, instead of a bare
+/// `unreachable` trap. With an `overlay` canvas, its animation loop is stopped and the panic is
+/// also drawn over it, so it shows without opening the developer tools.
+pub fn set_panic_hook(overlay: Option<&Canvas>) {
+    let overlay = overlay.cloned();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload.downcast_ref::<&str>().copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let report = match info.location() {
+            Some(location) => format!("panicked at {}:{}:{}:\n{}", location.file(), location.line(), location.column(), message),
+            None => format!("panicked:\n{}", message),
+        };
+        error(&report);
+
+        if let Some(canvas) = &overlay {
+            draw_panic_overlay(canvas, &report);
+        }
+    }));
+}
+
+fn draw_panic_overlay(canvas: &Canvas, report: &str) {
+    const FONT: &str = "14px monospace";
+    const LINE_HEIGHT: f32 = 18.0;
+
+    canvas.stop_animation_loop();
+    canvas.fill_rect(0.0, 0.0, canvas.width(), canvas.height(), 0.0, canvas::BLACK);
+    canvas.draw_text("WASM panic", 10.0, 24.0, "bold 16px monospace", canvas::RED);
+    for (line, text) in report.lines().enumerate() {
+        canvas.draw_text(text, 10.0, 24.0 + LINE_HEIGHT * (line + 2) as f32, FONT, canvas::WHITE);
+    }
+}
+
+// Macros formatting their arguments like `format!` ///////////////////////////////////////
+
+/// `console::log` with `format!` arguments
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => { $crate::console::log(&format!($($arg)*)) };
+}
+
+/// `console::error` with `format!` arguments
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::console::error(&format!($($arg)*)) };
+}
+
+/// `console::warn` with `format!` arguments
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => { $crate::console::warn(&format!($($arg)*)) };
+}
+
+/// `console::info` with `format!` arguments
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::console::info(&format!($($arg)*)) };
+}
+
+/// `console::debug` with `format!` arguments
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::console::debug(&format!($($arg)*)) };
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Errors reported by canvas operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// No element with this id in the page
+    MissingElement(String),
+    /// The element with this id is not a `<canvas>`
+    NotACanvas(String),
+    /// No 2D drawing context for the canvas with this id, or created in the element with this id,
+    /// e.g. because the canvas already has a WebGL context
+    ContextUnavailable(String),
+    /// No canvas with this id, e.g. because it was destroyed
+    UnknownCanvas(u32),
+    /// Sizes, coordinates or point lists that cannot be drawn
+    InvalidGeometry(String),
+    /// The browser dropped the drawing context of the canvas, until it restores it
+    ContextLost(u32),
+    /// A resource, like a font, is still loading
+    ResourceNotLoaded(String),
+    /// Fixed timestep settings that cannot drive an update loop
+    InvalidTimestep(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingElement(name) => write!(f, "no element with id '{}'", name),
+            Error::NotACanvas(name) => write!(f, "element '{}' is not a canvas", name),
+            Error::ContextUnavailable(name) => write!(f, "no 2D drawing context available at element '{}'", name),
+            Error::UnknownCanvas(id) => write!(f, "no canvas with id {}", id),
+            Error::InvalidGeometry(message) => write!(f, "invalid geometry: {}", message),
+            Error::ContextLost(id) => write!(f, "drawing context of canvas {} was lost", id),
+            Error::ResourceNotLoaded(name) => write!(f, "resource '{}' is not loaded", name),
+            Error::InvalidTimestep(message) => write!(f, "invalid timestep: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
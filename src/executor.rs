@@ -0,0 +1,162 @@
+// Single-threaded executor driven by the browser event loop ///////////////////////////////
+//
+// Spawned futures are polled from a microtask queued whenever one of them is woken, so async
+// code runs between browser events without blocking them. Futures are woken by timers, animation
+// frames or any other callback holding their `Waker`.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use crate::browser::{self, FrameId, TimerId};
+
+/// Run a future to completion in the background, e.g. a cutscene or a loading screen
+pub fn spawn_local(future: impl Future<Output = ()> + 'static) {
+    let id = NEXT_TASK_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+    TASKS.with(|tasks| tasks.borrow_mut().insert(id, Box::pin(future)));
+    schedule(id);
+}
+
+/// Future completing after `ms` milliseconds
+pub fn sleep(ms: u32) -> Sleep {
+    Sleep { ms, signal: Rc::new(Signal::default()), timer: None }
+}
+
+/// Future completing at the next animation frame, with the frame time in milliseconds
+pub fn next_frame() -> NextFrame {
+    NextFrame { signal: Rc::new(Signal::default()), frame: None }
+}
+
+/// Future returned by `sleep`. Dropping it cancels the timer
+pub struct Sleep {
+    ms: u32,
+    signal: Rc<Signal<()>>,
+    timer: Option<TimerId>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.timer.is_none() {
+            let signal = self.signal.clone();
+            self.timer = Some(browser::set_timeout(self.ms, move || signal.complete(())));
+        }
+        self.signal.poll(cx)
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(timer) = self.timer {
+            browser::clear_timeout(timer);
+        }
+    }
+}
+
+/// Future returned by `next_frame`. Dropping it cancels the frame request
+pub struct NextFrame {
+    signal: Rc<Signal<f64>>,
+    frame: Option<FrameId>,
+}
+
+impl Future for NextFrame {
+    type Output = f64;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<f64> {
+        if self.frame.is_none() {
+            let signal = self.signal.clone();
+            self.frame = Some(browser::request_animation_frame(move |time| signal.complete(time)));
+        }
+        self.signal.poll(cx)
+    }
+}
+
+impl Drop for NextFrame {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame {
+            browser::cancel_animation_frame(frame);
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+thread_local! {
+    static NEXT_TASK_ID: Cell<u32> = const { Cell::new(1) };
+    // Tasks waiting to be woken. A task is out of the map while it is polled, so it can spawn others
+    static TASKS: RefCell<HashMap<u32, Task>> = RefCell::new(HashMap::new());
+    static QUEUE: RefCell<VecDeque<u32>> = const { RefCell::new(VecDeque::new()) };
+    static RUN_SCHEDULED: Cell<bool> = const { Cell::new(false) };
+}
+
+// Wakers only carry the task id, the tasks themselves stay on this thread
+struct TaskWaker(u32);
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) { schedule(self.0); }
+}
+
+fn schedule(id: u32) {
+    QUEUE.with(|queue| queue.borrow_mut().push_back(id));
+    if !RUN_SCHEDULED.with(|scheduled| scheduled.replace(true)) {
+        unsafe { js::queue_microtask(); }
+    }
+}
+
+/// Poll every woken task
+#[no_mangle]
+pub extern "C" fn run_tasks() {
+    RUN_SCHEDULED.with(|scheduled| scheduled.set(false));
+    while let Some(id) = QUEUE.with(|queue| queue.borrow_mut().pop_front()) {
+        // Finished tasks, or tasks woken several times, are no longer in the map
+        let Some(mut task) = TASKS.with(|tasks| tasks.borrow_mut().remove(&id)) else { continue };
+        let waker = Waker::from(Arc::new(TaskWaker(id)));
+        if task.as_mut().poll(&mut Context::from_waker(&waker)).is_pending() {
+            TASKS.with(|tasks| tasks.borrow_mut().insert(id, task));
+        }
+    }
+}
+
+// Value set by a callback, waking the future waiting for it
+struct Signal<T> {
+    value: Cell<Option<T>>,
+    waker: RefCell<Option<Waker>>,
+}
+
+impl<T> Default for Signal<T> {
+    fn default() -> Self { Signal { value: Cell::new(None), waker: RefCell::new(None) } }
+}
+
+impl<T: Copy> Signal<T> {
+    fn complete(&self, value: T) {
+        self.value.set(Some(value));
+        if let Some(waker) = self.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    fn poll(&self, cx: &mut Context<'_>) -> Poll<T> {
+        match self.value.get() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                *self.waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// API imported from JavaScript at the browser //////////////////////////////////////////
+mod js {
+    #[link(wasm_import_module = "Browser")]
+    extern "C" {
+        pub fn queue_microtask();
+    }
+}
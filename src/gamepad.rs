@@ -0,0 +1,204 @@
+use std::cell::Cell;
+
+/// Buttons of the standard gamepad mapping, named by position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    South = 0, // A on Xbox, Cross on PlayStation
+    East = 1,  // B, Circle
+    West = 2,  // X, Square
+    North = 3, // Y, Triangle
+    LeftBumper = 4,
+    RightBumper = 5,
+    LeftTrigger = 6,
+    RightTrigger = 7,
+    Select = 8,
+    Start = 9,
+    LeftStick = 10,
+    RightStick = 11,
+    DPadUp = 12,
+    DPadDown = 13,
+    DPadLeft = 14,
+    DPadRight = 15,
+    Home = 16,
+}
+
+/// Axes of the standard gamepad mapping, in [-1, 1] with positive values right and down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    LeftX = 0,
+    LeftY = 1,
+    RightX = 2,
+    RightY = 3,
+}
+
+/// State of a connected gamepad when it was polled
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gamepad {
+    /// Slot of the gamepad, stable while it stays connected
+    pub index: u32,
+    /// Buttons and axes follow the standard mapping, so `Button` and `Axis` apply
+    pub standard_mapping: bool,
+    /// Analog value of each button in [0, 1]
+    pub buttons: Vec<f32>,
+    pub pressed: Vec<bool>,
+    /// Raw axis values, without dead zone
+    pub axes: Vec<f32>,
+}
+
+impl Gamepad {
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.get(button as usize).copied().unwrap_or(false)
+    }
+
+    pub fn button_value(&self, button: Button) -> f32 {
+        self.buttons.get(button as usize).copied().unwrap_or(0.0)
+    }
+
+    /// Axis value with the dead zone applied
+    pub fn axis(&self, axis: Axis) -> f32 { self.axis_value(axis as usize) }
+
+    /// Value of the axis at `index` with the dead zone applied, for any mapping
+    pub fn axis_value(&self, index: usize) -> f32 {
+        apply_dead_zone(self.raw_axis(index), dead_zone())
+    }
+
+    /// Left stick position, with a radial dead zone so diagonals are not distorted
+    pub fn left_stick(&self) -> (f32, f32) {
+        stick(self.raw_axis(Axis::LeftX as usize), self.raw_axis(Axis::LeftY as usize), dead_zone())
+    }
+
+    /// Right stick position, with a radial dead zone
+    pub fn right_stick(&self) -> (f32, f32) {
+        stick(self.raw_axis(Axis::RightX as usize), self.raw_axis(Axis::RightY as usize), dead_zone())
+    }
+
+    fn raw_axis(&self, axis: usize) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Read the state of every connected gamepad.
+///
+/// Browsers only expose gamepads once a button has been pressed while the page is visible.
+/// Canvases with a running animation loop poll gamepads every frame on their own and report
+/// changes through the `on_gamepad_*` callbacks of their event handler.
+pub fn gamepads() -> Vec<Gamepad> {
+    unsafe {
+        let count = js::snapshot();
+        (0..count)
+            .filter(|&index| js::connected(index))
+            .map(|index| Gamepad {
+                index,
+                standard_mapping: js::standard_mapping(index),
+                buttons: (0..js::button_count(index)).map(|b| js::button_value(index, b)).collect(),
+                pressed: (0..js::button_count(index)).map(|b| js::button_pressed(index, b)).collect(),
+                axes: (0..js::axis_count(index)).map(|a| js::axis_value(index, a)).collect(),
+            })
+            .collect()
+    }
+}
+
+/// Axis values below the dead zone read as 0, to hide stick drift. Defaults to 0.15,
+/// clamped to [0, 0.99]; non-finite values are ignored
+pub fn set_dead_zone(dead_zone: f32) {
+    if dead_zone.is_finite() {
+        DEAD_ZONE.with(|d| d.set(dead_zone.clamp(0.0, 0.99)));
+    }
+}
+pub fn dead_zone() -> f32 { DEAD_ZONE.with(|d| d.get()) }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+thread_local! {
+    static DEAD_ZONE: Cell<f32> = const { Cell::new(0.15) };
+}
+
+// Rescale so values start from 0 at the edge of the dead zone and still reach 1
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value.abs() <= dead_zone {
+        0.0
+    } else {
+        value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+    }
+}
+
+fn stick(x: f32, y: f32, dead_zone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    let scaled = apply_dead_zone(magnitude.min(1.0), dead_zone);
+    if scaled == 0.0 { (0.0, 0.0) } else { (x / magnitude * scaled, y / magnitude * scaled) }
+}
+
+/// API imported from JavaScript at the browser //////////////////////////////////////////
+mod js {
+    #[link(wasm_import_module = "Gamepad")]
+    extern "C" {
+        pub fn snapshot() -> u32;
+        pub fn connected(index: u32) -> bool;
+        pub fn standard_mapping(index: u32) -> bool;
+        pub fn button_count(index: u32) -> u32;
+        pub fn button_value(index: u32, button: u32) -> f32;
+        pub fn button_pressed(index: u32, button: u32) -> bool;
+        pub fn axis_count(index: u32) -> u32;
+        pub fn axis_value(index: u32, axis: u32) -> f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn dead_zone_rescales_axis() {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-0.2, 0.2), 0.0);
+        assert_close(apply_dead_zone(0.6, 0.2), 0.5);
+        assert_close(apply_dead_zone(-0.6, 0.2), -0.5);
+        assert_close(apply_dead_zone(1.0, 0.2), 1.0);
+        assert_close(apply_dead_zone(-1.0, 0.2), -1.0);
+        assert_close(apply_dead_zone(0.5, 0.0), 0.5);
+    }
+
+    #[test]
+    fn stick_dead_zone_is_radial() {
+        // Each axis alone is inside the dead zone, but the diagonal is not
+        let (x, y) = stick(0.3, 0.4, 0.4);
+        assert_close((x * x + y * y).sqrt(), (0.5 - 0.4) / 0.6);
+        assert_close(x / y, 0.75);
+
+        assert_eq!(stick(0.2, -0.2, 0.4), (0.0, 0.0));
+    }
+
+    #[test]
+    fn stick_keeps_direction_and_caps_magnitude() {
+        let (x, y) = stick(1.0, 1.0, 0.2);
+        assert_close((x * x + y * y).sqrt(), 1.0);
+        assert_close(x, y);
+
+        let (x, y) = stick(-0.6, 0.0, 0.2);
+        assert_close(x, -0.5);
+        assert_eq!(y, 0.0);
+    }
+
+    #[test]
+    fn stick_at_rest() {
+        assert_eq!(stick(0.0, 0.0, 0.0), (0.0, 0.0));
+        assert_eq!(stick(0.0, 0.0, 0.15), (0.0, 0.0));
+    }
+
+    #[test]
+    fn set_dead_zone_ignores_non_finite() {
+        set_dead_zone(0.3);
+        set_dead_zone(f32::NAN);
+        set_dead_zone(f32::INFINITY);
+        assert_eq!(dead_zone(), 0.3);
+
+        set_dead_zone(2.0);
+        assert_eq!(dead_zone(), 0.99);
+        set_dead_zone(-1.0);
+        assert_eq!(dead_zone(), 0.0);
+    }
+}
@@ -0,0 +1,380 @@
+use crate::canvas::{PointerEvent, WheelEvent};
+
+/// High-level gestures recognised from pointer and wheel events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Drag with one pointer, or movement of the center of two pointers
+    Pan { x: f32, y: f32, dx: f32, dy: f32 },
+    /// Change in distance between two pointers, as a factor relative to the previous event
+    Pinch { scale: f32, center: (f32, f32) },
+    /// Change in angle between two pointers, in radians since the previous event
+    Rotate { angle: f32, center: (f32, f32) },
+    Tap { x: f32, y: f32 },
+    /// Second tap close in time and space to a previous tap, reported instead of a `Tap`
+    DoubleTap { x: f32, y: f32 },
+    LongPress { x: f32, y: f32 },
+}
+
+/// Thresholds used to tell gestures apart. Times are in milliseconds, distances in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// Distance a pointer can move and still be a tap or long press instead of a pan
+    pub tap_slop: f32,
+    /// Longest press that counts as a tap
+    pub tap_timeout: f64,
+    /// Longest delay between the two taps of a double tap
+    pub double_tap_timeout: f64,
+    /// Largest distance between the two taps of a double tap
+    pub double_tap_slop: f32,
+    /// Time a pointer must stay down without moving to be a long press
+    pub long_press_timeout: f64,
+    /// Wheel delta, in pixels, that zooms by a factor e when pinching on a trackpad
+    pub wheel_pinch_scale: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            tap_slop: 10.0,
+            tap_timeout: 300.0,
+            double_tap_timeout: 300.0,
+            double_tap_slop: 30.0,
+            long_press_timeout: 500.0,
+            wheel_pinch_scale: 100.0,
+        }
+    }
+}
+
+struct Pointer {
+    id: i32,
+    start: (f32, f32),
+    position: (f32, f32),
+    start_time: f64,
+}
+
+/// Turns the pointer events delivered to an `EventHandler` into gestures.
+///
+/// Forward `on_pointer_*` events with the current time (e.g. `browser::time_now()`) and call
+/// `update` regularly, for instance from `on_animation_frame`, so long presses are detected
+/// while the pointer stays still. Nothing here talks to the browser, so gesture sequences can
+/// be reproduced by feeding synthetic events.
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    pointers: Vec<Pointer>,
+    // The current single pointer interaction can still be a tap or long press
+    tap_candidate: bool,
+    // Single pointer moved beyond the tap slop and is panning
+    panning: bool,
+    // Time and position of the last tap, to detect double taps
+    last_tap: Option<(f64, f32, f32)>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> GestureRecognizer {
+        GestureRecognizer { config, pointers: Vec::new(), tap_candidate: false, panning: false, last_tap: None }
+    }
+
+    pub fn config(&self) -> &GestureConfig { &self.config }
+    pub fn set_config(&mut self, config: GestureConfig) { self.config = config; }
+
+    /// Number of pointers currently down
+    pub fn active_pointers(&self) -> usize { self.pointers.len() }
+
+    pub fn pointer_down(&mut self, event: &PointerEvent, time: f64) -> Vec<Gesture> {
+        self.pointers.retain(|p| p.id != event.pointer_id);
+        self.pointers.push(Pointer {
+            id: event.pointer_id,
+            start: (event.x, event.y),
+            position: (event.x, event.y),
+            start_time: time,
+        });
+
+        // Taps and long presses only involve a single pointer
+        self.tap_candidate = self.pointers.len() == 1;
+        self.panning = false;
+        Vec::new()
+    }
+
+    pub fn pointer_move(&mut self, event: &PointerEvent, _time: f64) -> Vec<Gesture> {
+        let Some(index) = self.pointers.iter().position(|p| p.id == event.pointer_id) else {
+            return Vec::new(); // Hovering pointer, not part of a gesture
+        };
+
+        if self.pointers.len() == 1 {
+            let pointer = &mut self.pointers[0];
+            let mut previous = pointer.position;
+            pointer.position = (event.x, event.y);
+
+            if !self.panning && distance(pointer.start, pointer.position) > self.config.tap_slop {
+                // The first pan covers the whole movement since the pointer went down
+                previous = pointer.start;
+                self.panning = true;
+                self.tap_candidate = false;
+            }
+            if !self.panning {
+                return Vec::new();
+            }
+            return vec![Gesture::Pan { x: event.x, y: event.y, dx: event.x - previous.0, dy: event.y - previous.1 }];
+        }
+
+        // Two or more pointers, the first two drive pan, pinch and rotate
+        if index > 1 {
+            self.pointers[index].position = (event.x, event.y);
+            return Vec::new();
+        }
+        let (a, b) = (self.pointers[0].position, self.pointers[1].position);
+        self.pointers[index].position = (event.x, event.y);
+        let (new_a, new_b) = (self.pointers[0].position, self.pointers[1].position);
+
+        let center = midpoint(a, b);
+        let new_center = midpoint(new_a, new_b);
+        let mut gestures = Vec::new();
+
+        if new_center != center {
+            gestures.push(Gesture::Pan { x: new_center.0, y: new_center.1, dx: new_center.0 - center.0, dy: new_center.1 - center.1 });
+        }
+
+        let span = distance(a, b);
+        let new_span = distance(new_a, new_b);
+        if span > 0.0 && new_span != span {
+            gestures.push(Gesture::Pinch { scale: new_span / span, center: new_center });
+        }
+
+        let angle = normalize_angle(angle_between(new_a, new_b) - angle_between(a, b));
+        if angle != 0.0 {
+            gestures.push(Gesture::Rotate { angle, center: new_center });
+        }
+        gestures
+    }
+
+    pub fn pointer_up(&mut self, event: &PointerEvent, time: f64) -> Vec<Gesture> {
+        let Some(index) = self.pointers.iter().position(|p| p.id == event.pointer_id) else {
+            return Vec::new();
+        };
+        let pointer = self.pointers.remove(index);
+
+        let mut gestures = Vec::new();
+        if self.tap_candidate && self.pointers.is_empty() && time - pointer.start_time <= self.config.tap_timeout {
+            let (x, y) = (event.x, event.y);
+            match self.last_tap {
+                Some((tap_time, tap_x, tap_y))
+                    if time - tap_time <= self.config.double_tap_timeout
+                        && distance((tap_x, tap_y), (x, y)) <= self.config.double_tap_slop =>
+                {
+                    gestures.push(Gesture::DoubleTap { x, y });
+                    self.last_tap = None;
+                }
+                _ => {
+                    gestures.push(Gesture::Tap { x, y });
+                    self.last_tap = Some((time, x, y));
+                }
+            }
+        }
+
+        // A pointer left over from a multi-touch gesture keeps panning, it never becomes a tap
+        self.tap_candidate = false;
+        self.panning = !self.pointers.is_empty();
+        gestures
+    }
+
+    pub fn pointer_cancel(&mut self, event: &PointerEvent, _time: f64) -> Vec<Gesture> {
+        self.pointers.retain(|p| p.id != event.pointer_id);
+        self.tap_candidate = false;
+        self.panning = !self.pointers.is_empty();
+        Vec::new()
+    }
+
+    /// Trackpad scrolling pans and trackpad pinching zooms, like their touch screen counterparts
+    pub fn wheel(&mut self, event: &WheelEvent) -> Vec<Gesture> {
+        if event.is_pinch {
+            let scale = (-event.delta_y / self.config.wheel_pinch_scale).exp();
+            vec![Gesture::Pinch { scale, center: (event.x, event.y) }]
+        } else {
+            vec![Gesture::Pan { x: event.x, y: event.y, dx: -event.delta_x, dy: -event.delta_y }]
+        }
+    }
+
+    /// Report time-based gestures, i.e. long presses of a pointer that did not move
+    pub fn update(&mut self, time: f64) -> Vec<Gesture> {
+        if self.tap_candidate && self.pointers.len() == 1 {
+            let pointer = &self.pointers[0];
+            if time - pointer.start_time >= self.config.long_press_timeout {
+                self.tap_candidate = false;
+                return vec![Gesture::LongPress { x: pointer.position.0, y: pointer.position.1 }];
+            }
+        }
+        Vec::new()
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self { GestureRecognizer::new(GestureConfig::default()) }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+fn midpoint(a: (f32, f32), b: (f32, f32)) -> (f32, f32) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+fn angle_between(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (b.1 - a.1).atan2(b.0 - a.0)
+}
+
+// Wrap an angle difference into (-PI, PI]
+fn normalize_angle(angle: f32) -> f32 {
+    use std::f32::consts::PI;
+    if angle > PI {
+        angle - 2.0 * PI
+    } else if angle <= -PI {
+        angle + 2.0 * PI
+    } else {
+        angle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{Modifiers, MouseButton, MouseButtons, PointerType};
+
+    fn pointer(pointer_id: i32, x: f32, y: f32) -> PointerEvent {
+        PointerEvent {
+            pointer_id,
+            pointer_type: PointerType::Touch,
+            is_primary: pointer_id == 1,
+            x,
+            y,
+            pressure: 0.5,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            width: 1.0,
+            height: 1.0,
+            button: MouseButton::Left,
+            buttons: MouseButtons(1),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    // Down and up at the same place, `duration` ms apart
+    fn tap(recognizer: &mut GestureRecognizer, x: f32, y: f32, time: f64, duration: f64) -> Vec<Gesture> {
+        recognizer.pointer_down(&pointer(1, x, y), time);
+        recognizer.pointer_up(&pointer(1, x, y), time + duration)
+    }
+
+    #[test]
+    fn movement_within_tap_slop_is_a_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        assert_eq!(recognizer.pointer_move(&pointer(1, 10.0, 0.0), 50.0), vec![]);
+        assert_eq!(recognizer.pointer_up(&pointer(1, 10.0, 0.0), 100.0), vec![Gesture::Tap { x: 10.0, y: 0.0 }]);
+    }
+
+    #[test]
+    fn movement_beyond_tap_slop_pans_from_the_start() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        assert_eq!(recognizer.pointer_move(&pointer(1, 11.0, 0.0), 50.0), vec![Gesture::Pan { x: 11.0, y: 0.0, dx: 11.0, dy: 0.0 }]);
+        assert_eq!(recognizer.pointer_move(&pointer(1, 11.0, 2.0), 60.0), vec![Gesture::Pan { x: 11.0, y: 2.0, dx: 0.0, dy: 2.0 }]);
+        assert_eq!(recognizer.pointer_up(&pointer(1, 11.0, 2.0), 100.0), vec![]);
+    }
+
+    #[test]
+    fn long_tap_is_not_a_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(tap(&mut recognizer, 0.0, 0.0, 0.0, 301.0), vec![]);
+    }
+
+    #[test]
+    fn double_tap_within_timeout_and_slop() {
+        let mut recognizer = GestureRecognizer::default();
+        assert_eq!(tap(&mut recognizer, 0.0, 0.0, 0.0, 50.0), vec![Gesture::Tap { x: 0.0, y: 0.0 }]);
+        assert_eq!(tap(&mut recognizer, 20.0, 0.0, 200.0, 50.0), vec![Gesture::DoubleTap { x: 20.0, y: 0.0 }]);
+        // The double tap used up the first tap
+        assert_eq!(tap(&mut recognizer, 20.0, 0.0, 300.0, 50.0), vec![Gesture::Tap { x: 20.0, y: 0.0 }]);
+    }
+
+    #[test]
+    fn double_tap_outside_timeout() {
+        let mut recognizer = GestureRecognizer::default();
+        tap(&mut recognizer, 0.0, 0.0, 0.0, 50.0);
+        assert_eq!(tap(&mut recognizer, 0.0, 0.0, 301.0, 50.0), vec![Gesture::Tap { x: 0.0, y: 0.0 }]);
+    }
+
+    #[test]
+    fn double_tap_outside_slop() {
+        let mut recognizer = GestureRecognizer::default();
+        tap(&mut recognizer, 0.0, 0.0, 0.0, 50.0);
+        assert_eq!(tap(&mut recognizer, 31.0, 0.0, 100.0, 50.0), vec![Gesture::Tap { x: 31.0, y: 0.0 }]);
+    }
+
+    #[test]
+    fn long_press_is_reported_once_by_update() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 5.0, 5.0), 0.0);
+        assert_eq!(recognizer.update(499.0), vec![]);
+        assert_eq!(recognizer.update(500.0), vec![Gesture::LongPress { x: 5.0, y: 5.0 }]);
+        assert_eq!(recognizer.update(600.0), vec![]);
+        assert_eq!(recognizer.pointer_up(&pointer(1, 5.0, 5.0), 700.0), vec![]);
+    }
+
+    #[test]
+    fn panning_pointer_is_no_long_press() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        recognizer.pointer_move(&pointer(1, 20.0, 0.0), 100.0);
+        assert_eq!(recognizer.update(1000.0), vec![]);
+    }
+
+    #[test]
+    fn two_pointers_pinch() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        recognizer.pointer_down(&pointer(2, 10.0, 0.0), 10.0);
+        assert_eq!(recognizer.active_pointers(), 2);
+        assert_eq!(recognizer.pointer_move(&pointer(2, 20.0, 0.0), 20.0), vec![
+            Gesture::Pan { x: 10.0, y: 0.0, dx: 5.0, dy: 0.0 },
+            Gesture::Pinch { scale: 2.0, center: (10.0, 0.0) },
+        ]);
+    }
+
+    #[test]
+    fn two_pointers_rotate() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        recognizer.pointer_down(&pointer(2, 10.0, 0.0), 10.0);
+        let gestures = recognizer.pointer_move(&pointer(2, 0.0, 10.0), 20.0);
+
+        assert_eq!(gestures.len(), 2, "{:?}", gestures);
+        assert_eq!(gestures[0], Gesture::Pan { x: 0.0, y: 5.0, dx: -5.0, dy: 5.0 });
+        let Gesture::Rotate { angle, center } = gestures[1] else { panic!("expected a rotation, got {:?}", gestures[1]) };
+        assert!((angle - std::f32::consts::FRAC_PI_2).abs() < 1e-6, "angle {}", angle);
+        assert_eq!(center, (0.0, 5.0));
+    }
+
+    #[test]
+    fn cancel_ends_the_gesture_of_its_pointer() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        assert_eq!(recognizer.pointer_cancel(&pointer(1, 0.0, 0.0), 50.0), vec![]);
+        assert_eq!(recognizer.active_pointers(), 0);
+        // Neither the late up event nor time passing turn it into a tap or long press
+        assert_eq!(recognizer.pointer_up(&pointer(1, 0.0, 0.0), 100.0), vec![]);
+        assert_eq!(recognizer.update(1000.0), vec![]);
+    }
+
+    #[test]
+    fn cancel_during_pinch_leaves_the_other_pointer_panning() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.pointer_down(&pointer(1, 0.0, 0.0), 0.0);
+        recognizer.pointer_down(&pointer(2, 10.0, 0.0), 10.0);
+        recognizer.pointer_cancel(&pointer(2, 10.0, 0.0), 20.0);
+
+        assert_eq!(recognizer.pointer_move(&pointer(1, 2.0, 0.0), 30.0), vec![Gesture::Pan { x: 2.0, y: 0.0, dx: 2.0, dy: 0.0 }]);
+        assert_eq!(recognizer.pointer_up(&pointer(1, 2.0, 0.0), 40.0), vec![]);
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use crate::canvas::{KeyCode, MouseButton};
+use crate::gamepad::Gamepad;
+
+/// Input held on a canvas, for polling from `on_animation_frame` instead of reacting to events.
+///
+/// Keys are tracked by physical position (`KeyEvent::code`), so games keep the same layout on
+/// every keyboard. The "just pressed" and "just released" sets gather everything that happened
+/// since the previous animation frame and are cleared once the frame has been handled.
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    keys_down: HashSet<KeyCode>,
+    keys_pressed: HashSet<KeyCode>,
+    keys_released: HashSet<KeyCode>,
+    buttons_down: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    mouse_position: (f32, f32),
+    // Gamepad buttons (standard mapping index) and axes, merged over all connected gamepads
+    gamepad_buttons_down: HashSet<u32>,
+    gamepad_buttons_pressed: HashSet<u32>,
+    gamepad_buttons_released: HashSet<u32>,
+    gamepad_axes: Vec<f32>,
+}
+
+impl InputState {
+    pub fn is_key_down(&self, key: KeyCode) -> bool { self.keys_down.contains(&key) }
+    pub fn is_key_just_pressed(&self, key: KeyCode) -> bool { self.keys_pressed.contains(&key) }
+    pub fn is_key_just_released(&self, key: KeyCode) -> bool { self.keys_released.contains(&key) }
+
+    pub fn is_button_down(&self, button: MouseButton) -> bool { self.buttons_down.contains(&button) }
+    pub fn is_button_just_pressed(&self, button: MouseButton) -> bool { self.buttons_pressed.contains(&button) }
+    pub fn is_button_just_released(&self, button: MouseButton) -> bool { self.buttons_released.contains(&button) }
+
+    pub fn is_gamepad_button_down(&self, button: u32) -> bool { self.gamepad_buttons_down.contains(&button) }
+    pub fn is_gamepad_button_just_pressed(&self, button: u32) -> bool { self.gamepad_buttons_pressed.contains(&button) }
+    pub fn is_gamepad_button_just_released(&self, button: u32) -> bool { self.gamepad_buttons_released.contains(&button) }
+    /// Gamepad axis value in [-1, 1], 0 when no gamepad reports it
+    pub fn gamepad_axis(&self, axis: u32) -> f32 { self.gamepad_axes.get(axis as usize).copied().unwrap_or(0.0) }
+
+    /// Keys currently held down
+    pub fn keys_down(&self) -> impl Iterator<Item = &KeyCode> { self.keys_down.iter() }
+
+    /// Last known mouse position over the canvas
+    pub fn mouse_position(&self) -> (f32, f32) { self.mouse_position }
+
+    ///////////////////////////////////////////////////////////////////////////////////////////////
+    // UPDATES FROM CANVAS EVENTS
+
+    pub(crate) fn key_down(&mut self, key: KeyCode) {
+        // Auto-repeat events are not new presses
+        if self.keys_down.insert(key) {
+            self.keys_pressed.insert(key);
+        }
+    }
+
+    pub(crate) fn key_up(&mut self, key: KeyCode) {
+        if self.keys_down.remove(&key) {
+            self.keys_released.insert(key);
+        }
+    }
+
+    pub(crate) fn button_down(&mut self, button: MouseButton) {
+        if self.buttons_down.insert(button) {
+            self.buttons_pressed.insert(button);
+        }
+    }
+
+    pub(crate) fn button_up(&mut self, button: MouseButton) {
+        if self.buttons_down.remove(&button) {
+            self.buttons_released.insert(button);
+        }
+    }
+
+    pub(crate) fn mouse_move(&mut self, x: f32, y: f32) { self.mouse_position = (x, y); }
+
+    /// Release everything held, e.g. when the canvas loses focus and key up events go elsewhere
+    pub(crate) fn release_all(&mut self) {
+        self.keys_released.extend(self.keys_down.drain());
+        self.buttons_released.extend(self.buttons_down.drain());
+    }
+
+    /// Merge the buttons and axes of every connected gamepad
+    pub(crate) fn update_gamepads(&mut self, gamepads: &[Gamepad]) {
+        let mut down = HashSet::new();
+        let mut axes: Vec<f32> = Vec::new();
+        for gamepad in gamepads {
+            down.extend(gamepad.pressed.iter().enumerate().filter(|(_, &p)| p).map(|(b, _)| b as u32));
+            axes.resize(axes.len().max(gamepad.axes.len()), 0.0);
+            for (axis, value) in axes.iter_mut().enumerate() {
+                // Strongest value wins when several gamepads are connected
+                let pad_value = gamepad.axis_value(axis);
+                if pad_value.abs() > value.abs() { *value = pad_value; }
+            }
+        }
+
+        self.gamepad_buttons_pressed.extend(down.difference(&self.gamepad_buttons_down));
+        self.gamepad_buttons_released.extend(self.gamepad_buttons_down.difference(&down));
+        self.gamepad_buttons_down = down;
+        self.gamepad_axes = axes;
+    }
+
+    /// Start a new frame, forgetting what was just pressed or released
+    pub(crate) fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.gamepad_buttons_pressed.clear();
+        self.gamepad_buttons_released.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_repeat_is_not_a_new_press() {
+        let mut input = InputState::default();
+        input.key_down(KeyCode::Space);
+        input.end_frame();
+        input.key_down(KeyCode::Space);
+        assert!(input.is_key_down(KeyCode::Space));
+        assert!(!input.is_key_just_pressed(KeyCode::Space));
+    }
+
+    #[test]
+    fn just_pressed_and_released_last_one_frame() {
+        let mut input = InputState::default();
+        input.key_down(KeyCode::ArrowLeft);
+        input.button_down(MouseButton::Left);
+        assert!(input.is_key_just_pressed(KeyCode::ArrowLeft));
+        assert!(input.is_button_just_pressed(MouseButton::Left));
+
+        input.end_frame();
+        assert!(input.is_key_down(KeyCode::ArrowLeft));
+        assert!(!input.is_key_just_pressed(KeyCode::ArrowLeft));
+        assert!(!input.is_button_just_pressed(MouseButton::Left));
+
+        input.key_up(KeyCode::ArrowLeft);
+        input.button_up(MouseButton::Left);
+        assert!(!input.is_key_down(KeyCode::ArrowLeft));
+        assert!(input.is_key_just_released(KeyCode::ArrowLeft));
+        assert!(input.is_button_just_released(MouseButton::Left));
+
+        input.end_frame();
+        assert!(!input.is_key_just_released(KeyCode::ArrowLeft));
+        assert!(!input.is_button_just_released(MouseButton::Left));
+    }
+
+    #[test]
+    fn press_and_release_within_a_frame() {
+        let mut input = InputState::default();
+        input.key_down(KeyCode::Space);
+        input.key_up(KeyCode::Space);
+        assert!(!input.is_key_down(KeyCode::Space));
+        assert!(input.is_key_just_pressed(KeyCode::Space));
+        assert!(input.is_key_just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn key_up_without_key_down_is_ignored() {
+        let mut input = InputState::default();
+        input.key_up(KeyCode::Space);
+        assert!(!input.is_key_just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn blur_releases_everything_held() {
+        let mut input = InputState::default();
+        input.key_down(KeyCode::ArrowRight);
+        input.button_down(MouseButton::Right);
+        input.end_frame();
+
+        input.release_all();
+        assert_eq!(input.keys_down().count(), 0);
+        assert!(!input.is_button_down(MouseButton::Right));
+        assert!(input.is_key_just_released(KeyCode::ArrowRight));
+        assert!(input.is_button_just_released(MouseButton::Right));
+
+        // The key up arriving after focus came back must not release it twice
+        input.end_frame();
+        input.key_up(KeyCode::ArrowRight);
+        assert!(!input.is_key_just_released(KeyCode::ArrowRight));
+    }
+}
@@ -0,0 +1,391 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::canvas::{KeyCode, MouseButton};
+use crate::input::InputState;
+
+/// Physical input that can trigger an action
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Gamepad button, by its index in the standard gamepad mapping
+    GamepadButton(u32),
+}
+
+/// Input driving an axis, with values in [-1, 1]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisBinding {
+    /// A pair of bindings, giving -1 while `negative` is held and 1 while `positive` is held
+    Buttons { negative: Binding, positive: Binding },
+    /// Gamepad axis, by its index in the standard gamepad mapping
+    GamepadAxis(u32),
+}
+
+/// Error found while reading bindings from text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Line of the error, starting at 1
+    pub line: usize,
+    pub message: String,
+}
+
+/// Binding refused by `InputMap::bind` or `InputMap::bind_axis`, as it could not be saved as text
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindError {
+    /// Empty name, or name containing whitespace, '=' or ','
+    InvalidName(String),
+    /// `KeyCode::Unknown` or `MouseButton::Unknown`, which stand for no particular input
+    UnknownInput(Binding),
+}
+
+/// Maps named actions ("jump") and axes ("move_x") to keys, mouse buttons and gamepad inputs.
+///
+/// Several bindings can trigger the same action. Bindings are evaluated against the polled
+/// `InputState` of a canvas and can be saved to and loaded from a simple text format, one
+/// action or axis per line:
+///
+/// ```text
+/// action jump = key:Space, gamepad:0
+/// axis move_x = key:ArrowLeft/key:ArrowRight, gamepad_axis:0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputMap {
+    // Kept in insertion order so the text format is stable
+    actions: Vec<(String, Vec<Binding>)>,
+    axes: Vec<(String, Vec<AxisBinding>)>,
+}
+
+impl InputMap {
+    pub fn new() -> InputMap { InputMap::default() }
+
+    /// Add a binding to an action, creating the action if needed
+    pub fn bind(&mut self, action: &str, binding: Binding) -> Result<(), BindError> {
+        check_name(action)?;
+        check_binding(binding)?;
+        let bindings = entry(&mut self.actions, action);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Add a binding to an axis, creating the axis if needed
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) -> Result<(), BindError> {
+        check_name(axis)?;
+        if let AxisBinding::Buttons { negative, positive } = binding {
+            check_binding(negative)?;
+            check_binding(positive)?;
+        }
+        let bindings = entry(&mut self.axes, axis);
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Remove one binding from an action
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some((_, bindings)) = self.actions.iter_mut().find(|(name, _)| name == action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Remove every binding of an action, e.g. before the player rebinds it
+    pub fn clear_action(&mut self, action: &str) {
+        self.actions.retain(|(name, _)| name != action);
+    }
+
+    /// Remove every binding of an axis
+    pub fn clear_axis(&mut self, axis: &str) {
+        self.axes.retain(|(name, _)| name != axis);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.iter().find(|(name, _)| name == action).map_or(&[], |(_, b)| b.as_slice())
+    }
+
+    pub fn axis_bindings(&self, axis: &str) -> &[AxisBinding] {
+        self.axes.iter().find(|(name, _)| name == axis).map_or(&[], |(_, b)| b.as_slice())
+    }
+
+    /// True while any binding of the action is held
+    pub fn is_action_down(&self, action: &str, input: &InputState) -> bool {
+        self.action_bindings(action).iter().any(|b| is_down(b, input))
+    }
+
+    /// True on the frame any binding of the action was pressed
+    pub fn is_action_just_pressed(&self, action: &str, input: &InputState) -> bool {
+        self.action_bindings(action).iter().any(|b| is_just_pressed(b, input))
+    }
+
+    /// True on the frame any binding of the action was released
+    pub fn is_action_just_released(&self, action: &str, input: &InputState) -> bool {
+        self.action_bindings(action).iter().any(|b| is_just_released(b, input))
+    }
+
+    /// Value of an axis in [-1, 1]. With several bindings active, the strongest one wins
+    pub fn axis(&self, axis: &str, input: &InputState) -> f32 {
+        self.axis_bindings(axis)
+            .iter()
+            .map(|binding| match binding {
+                AxisBinding::Buttons { negative, positive } => {
+                    let negative = if is_down(negative, input) { -1.0 } else { 0.0 };
+                    let positive = if is_down(positive, input) { 1.0 } else { 0.0 };
+                    negative + positive
+                }
+                AxisBinding::GamepadAxis(index) => input.gamepad_axis(*index),
+            })
+            .fold(0.0f32, |strongest, value| if value.abs() > strongest.abs() { value } else { strongest })
+            .clamp(-1.0, 1.0)
+    }
+
+    /// Write every binding in the text format read by `from_text`
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, bindings) in &self.actions {
+            let list: Vec<String> = bindings.iter().map(|b| b.to_string()).collect();
+            text.push_str(&format!("action {} = {}\n", name, list.join(", ")));
+        }
+        for (name, bindings) in &self.axes {
+            let list: Vec<String> = bindings.iter().map(|b| b.to_string()).collect();
+            text.push_str(&format!("axis {} = {}\n", name, list.join(", ")));
+        }
+        text
+    }
+
+    /// Read bindings written by `to_text`. Empty lines and lines starting with `#` are ignored
+    pub fn from_text(text: &str) -> Result<InputMap, ParseError> {
+        let mut map = InputMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ParseError { line: index + 1, message };
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (declaration, bindings) = line.split_once('=').ok_or_else(|| error("expected '='".to_string()))?;
+            let (kind, name) = declaration.trim().split_once(char::is_whitespace)
+                .ok_or_else(|| error("expected 'action <name>' or 'axis <name>'".to_string()))?;
+            let name = name.trim();
+            check_name(name).map_err(|err| error(err.to_string()))?;
+
+            let bindings = bindings.split(',').map(str::trim).filter(|b| !b.is_empty());
+            match kind {
+                "action" => {
+                    entry(&mut map.actions, name);
+                    for binding in bindings {
+                        map.bind(name, binding.parse().map_err(error)?).map_err(|err| error(err.to_string()))?;
+                    }
+                }
+                "axis" => {
+                    entry(&mut map.axes, name);
+                    for binding in bindings {
+                        map.bind_axis(name, binding.parse().map_err(error)?).map_err(|err| error(err.to_string()))?;
+                    }
+                }
+                _ => return Err(error(format!("unknown declaration '{}'", kind))),
+            }
+        }
+        Ok(map)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn entry<'a, T>(list: &'a mut Vec<(String, Vec<T>)>, name: &str) -> &'a mut Vec<T> {
+    let index = match list.iter().position(|(n, _)| n == name) {
+        Some(index) => index,
+        None => {
+            list.push((name.to_string(), Vec::new()));
+            list.len() - 1
+        }
+    };
+    &mut list[index].1
+}
+
+// Names and bindings must survive `to_text` and `from_text`
+fn check_name(name: &str) -> Result<(), BindError> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '=' || c == ',') {
+        return Err(BindError::InvalidName(name.to_string()));
+    }
+    Ok(())
+}
+
+fn check_binding(binding: Binding) -> Result<(), BindError> {
+    match binding {
+        Binding::Key(KeyCode::Unknown) | Binding::Mouse(MouseButton::Unknown) => Err(BindError::UnknownInput(binding)),
+        _ => Ok(()),
+    }
+}
+
+fn is_down(binding: &Binding, input: &InputState) -> bool {
+    match binding {
+        Binding::Key(key) => input.is_key_down(*key),
+        Binding::Mouse(button) => input.is_button_down(*button),
+        Binding::GamepadButton(button) => input.is_gamepad_button_down(*button),
+    }
+}
+
+fn is_just_pressed(binding: &Binding, input: &InputState) -> bool {
+    match binding {
+        Binding::Key(key) => input.is_key_just_pressed(*key),
+        Binding::Mouse(button) => input.is_button_just_pressed(*button),
+        Binding::GamepadButton(button) => input.is_gamepad_button_just_pressed(*button),
+    }
+}
+
+fn is_just_released(binding: &Binding, input: &InputState) -> bool {
+    match binding {
+        Binding::Key(key) => input.is_key_just_released(*key),
+        Binding::Mouse(button) => input.is_button_just_released(*button),
+        Binding::GamepadButton(button) => input.is_gamepad_button_just_released(*button),
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "key:{}", key.name()),
+            Binding::Mouse(button) => write!(f, "mouse:{}", button.name()),
+            Binding::GamepadButton(button) => write!(f, "gamepad:{}", button),
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = text.split_once(':').ok_or_else(|| format!("invalid binding '{}'", text))?;
+        let binding = match kind {
+            "key" => KeyCode::from_name(value).map(Binding::Key),
+            "mouse" => MouseButton::from_name(value).map(Binding::Mouse),
+            "gamepad" => value.parse().ok().map(Binding::GamepadButton),
+            _ => None,
+        };
+        binding.ok_or_else(|| format!("invalid binding '{}'", text))
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AxisBinding::Buttons { negative, positive } => write!(f, "{}/{}", negative, positive),
+            AxisBinding::GamepadAxis(axis) => write!(f, "gamepad_axis:{}", axis),
+        }
+    }
+}
+
+impl FromStr for AxisBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if let Some((negative, positive)) = text.split_once('/') {
+            return Ok(AxisBinding::Buttons { negative: negative.trim().parse()?, positive: positive.trim().parse()? });
+        }
+        match text.split_once(':') {
+            Some(("gamepad_axis", axis)) => axis.parse().map(AxisBinding::GamepadAxis)
+                .map_err(|_| format!("invalid axis binding '{}'", text)),
+            _ => Err(format!("invalid axis binding '{}'", text)),
+        }
+    }
+}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BindError::InvalidName(name) => write!(f, "invalid name '{}'", name),
+            BindError::UnknownInput(binding) => write!(f, "cannot bind unknown input '{}'", binding),
+        }
+    }
+}
+
+impl std::error::Error for BindError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> InputMap {
+        let mut map = InputMap::new();
+        map.bind("jump", Binding::Key(KeyCode::Space)).unwrap();
+        map.bind("jump", Binding::GamepadButton(0)).unwrap();
+        map.bind("fire", Binding::Mouse(MouseButton::Left)).unwrap();
+        map.bind_axis("move_x", AxisBinding::Buttons {
+            negative: Binding::Key(KeyCode::ArrowLeft),
+            positive: Binding::Key(KeyCode::ArrowRight),
+        }).unwrap();
+        map.bind_axis("move_x", AxisBinding::GamepadAxis(0)).unwrap();
+        map
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let map = sample();
+        let text = map.to_text();
+        assert_eq!(text, "action jump = key:Space, gamepad:0\n\
+                          action fire = mouse:Left\n\
+                          axis move_x = key:ArrowLeft/key:ArrowRight, gamepad_axis:0\n");
+        assert_eq!(InputMap::from_text(&text), Ok(map));
+    }
+
+    #[test]
+    fn empty_actions_round_trip() {
+        let map = InputMap::from_text("action pause =\naxis zoom =\n").unwrap();
+        assert_eq!(map.to_text(), "action pause = \naxis zoom = \n");
+        assert_eq!(InputMap::from_text(&map.to_text()), Ok(map));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let map = InputMap::from_text("# Controls\n\n  action jump = key:Space  \n").unwrap();
+        assert_eq!(map.action_bindings("jump"), &[Binding::Key(KeyCode::Space)]);
+    }
+
+    #[test]
+    fn names_that_cannot_be_written_are_refused() {
+        let mut map = InputMap::new();
+        for name in ["", "my action", "a=b", "a,b"] {
+            assert_eq!(map.bind(name, Binding::Key(KeyCode::Space)), Err(BindError::InvalidName(name.to_string())));
+            assert_eq!(map.bind_axis(name, AxisBinding::GamepadAxis(0)), Err(BindError::InvalidName(name.to_string())));
+        }
+        assert_eq!(map, InputMap::new());
+    }
+
+    #[test]
+    fn unknown_inputs_are_refused() {
+        let mut map = InputMap::new();
+        let key = Binding::Key(KeyCode::Unknown);
+        let button = Binding::Mouse(MouseButton::Unknown);
+        assert_eq!(map.bind("jump", key), Err(BindError::UnknownInput(key)));
+        assert_eq!(map.bind("jump", button), Err(BindError::UnknownInput(button)));
+        let axis = AxisBinding::Buttons { negative: Binding::Key(KeyCode::A), positive: key };
+        assert_eq!(map.bind_axis("move_x", axis), Err(BindError::UnknownInput(key)));
+        assert_eq!(map, InputMap::new());
+    }
+
+    #[test]
+    fn parse_errors_report_their_line() {
+        let error = |text: &str| InputMap::from_text(text).unwrap_err();
+
+        assert_eq!(error("action jump key:Space"), ParseError { line: 1, message: "expected '='".to_string() });
+        assert_eq!(error("\njump = key:Space").line, 2);
+        assert_eq!(error("button jump = key:Space").message, "unknown declaration 'button'");
+        assert_eq!(error("action my jump = key:Space").message, "invalid name 'my jump'");
+        assert_eq!(error("action jump = key:Unknown").message, "invalid binding 'key:Unknown'");
+        assert_eq!(error("action jump = mouse:Unknown").message, "invalid binding 'mouse:Unknown'");
+        assert_eq!(error("action jump = gamepad:x").message, "invalid binding 'gamepad:x'");
+        assert_eq!(error("axis move_x = key:A").message, "invalid axis binding 'key:A'");
+        assert_eq!(error("axis move_x = key:A/key:Nope").message, "invalid binding 'key:Nope'");
+    }
+}
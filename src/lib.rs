@@ -1,3 +1,15 @@
 pub mod canvas;
 pub mod console;
-pub mod browser;
\ No newline at end of file
+pub mod error;
+pub mod executor;
+pub mod browser;
+pub mod gamepad;
+pub mod gesture;
+pub mod input;
+pub mod input_map;
+pub mod listener;
+pub mod timestep;
+
+mod memory;
+
+pub use error::Error;
\ No newline at end of file
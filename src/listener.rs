@@ -0,0 +1,113 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use crate::canvas::{Canvas, FileError, KeyEvent, MouseEvent, PointerEvent, WheelEvent};
+use crate::gamepad::Gamepad;
+
+/// Keeps a closure registered with `Canvas::on_mouse_down` and friends alive.
+///
+/// The closure is removed when the handle is dropped, unless `forget` was called.
+#[must_use = "the listener is removed as soon as the handle is dropped"]
+pub struct ListenerHandle {
+    id: u32,
+    active: bool,
+}
+
+impl ListenerHandle {
+    /// Remove the listener now
+    pub fn remove(self) {}
+
+    /// Keep the listener for as long as the canvas lives
+    pub fn forget(mut self) { self.active = false; }
+}
+
+impl Drop for ListenerHandle {
+    fn drop(&mut self) {
+        if self.active {
+            LISTENERS.with(|listeners| listeners.borrow_mut().remove(&self.id));
+        }
+    }
+}
+
+type EventCallback<E> = Box<dyn FnMut(&Canvas, &E)>;
+type FrameCallback = Box<dyn FnMut(&Canvas, f32)>;
+type ResizeCallback = Box<dyn FnMut(&Canvas, f32, f32)>;
+type StateCallback = Box<dyn FnMut(&Canvas, bool)>;
+type FileDroppedCallback = Box<dyn FnMut(&Canvas, &str, &str, &[u8], f32, f32)>;
+type FileErrorCallback = Box<dyn FnMut(&Canvas, &str, FileError)>;
+type IndexCallback = Box<dyn FnMut(&Canvas, u32)>;
+type GamepadButtonCallback = Box<dyn FnMut(&Canvas, &Gamepad, u32, bool)>;
+
+pub(crate) enum Listener {
+    MouseMove(EventCallback<MouseEvent>),
+    MouseDown(EventCallback<MouseEvent>),
+    MouseUp(EventCallback<MouseEvent>),
+    DoubleClick(EventCallback<MouseEvent>),
+    MouseEnter(EventCallback<MouseEvent>),
+    MouseLeave(EventCallback<MouseEvent>),
+    Wheel(EventCallback<WheelEvent>),
+    PointerDown(EventCallback<PointerEvent>),
+    PointerMove(EventCallback<PointerEvent>),
+    PointerUp(EventCallback<PointerEvent>),
+    PointerCancel(EventCallback<PointerEvent>),
+    KeyDown(EventCallback<KeyEvent>),
+    KeyUp(EventCallback<KeyEvent>),
+    TextInput(EventCallback<str>),
+    TextComposition(EventCallback<str>),
+    AnimationFrame(FrameCallback),
+    Resize(ResizeCallback),
+    Focus(Box<dyn FnMut(&Canvas)>),
+    Blur(Box<dyn FnMut(&Canvas)>),
+    VisibilityChange(StateCallback),
+    PointerLockChange(StateCallback),
+    FullscreenChange(StateCallback),
+    FileDropped(FileDroppedCallback),
+    FileError(FileErrorCallback),
+    GamepadConnected(EventCallback<Gamepad>),
+    GamepadDisconnected(IndexCallback),
+    GamepadButton(GamepadButtonCallback),
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+
+thread_local! {
+    static NEXT_LISTENER_ID: Cell<u32> = const { Cell::new(1) };
+    // Listeners by id, so they run in registration order, with the id of their canvas. A listener
+    // is out of its slot while it runs, so it can add or remove listeners, including itself
+    static LISTENERS: RefCell<BTreeMap<u32, (u32, Option<Listener>)>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+pub(crate) fn add(canvas: &Canvas, listener: Listener) -> ListenerHandle {
+    let id = NEXT_LISTENER_ID.with(|next| next.replace(next.get().wrapping_add(1)));
+    LISTENERS.with(|listeners| listeners.borrow_mut().insert(id, (canvas.id(), Some(listener))));
+    ListenerHandle { id, active: true }
+}
+
+/// Remove every listener of the canvas, their handles become inert
+pub(crate) fn remove_all(canvas: &Canvas) {
+    // Dropped once the registry is released, closures may own values that use it in their drop
+    let _removed: Vec<_> = LISTENERS.with(|listeners| {
+        let mut listeners = listeners.borrow_mut();
+        let ids: Vec<u32> = listeners.iter().filter(|(_, (c, _))| *c == canvas.id()).map(|(id, _)| *id).collect();
+        ids.into_iter().filter_map(|id| listeners.remove(&id)).collect()
+    });
+}
+
+/// Run `f` with every listener of the canvas. Listeners added meanwhile wait for the next event
+pub(crate) fn emit(canvas: &Canvas, mut f: impl FnMut(&mut Listener, &Canvas)) {
+    let ids: Vec<u32> = LISTENERS.with(|listeners| {
+        listeners.borrow().iter().filter(|(_, (canvas_id, _))| *canvas_id == canvas.id()).map(|(id, _)| *id).collect()
+    });
+
+    for id in ids {
+        let listener = LISTENERS.with(|listeners| listeners.borrow_mut().get_mut(&id).and_then(|(_, l)| l.take()));
+        let Some(mut listener) = listener else { continue };
+        f(&mut listener, canvas);
+        // Put it back, unless it was removed while running
+        LISTENERS.with(|listeners| {
+            if let Some((_, slot)) = listeners.borrow_mut().get_mut(&id) {
+                *slot = Some(listener);
+            }
+        });
+    }
+}
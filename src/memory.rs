@@ -0,0 +1,25 @@
+// Buffers handed over from JavaScript ////////////////////////////////////////////////////
+//
+// The browser asks for a buffer with `wasm_alloc`, writes its data into it and passes the
+// pointer to an exported entry point, which takes back ownership with `take_bytes`.
+
+#[no_mangle]
+pub extern "C" fn wasm_alloc(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()) as *mut u8
+}
+
+/// Take ownership of a buffer created by `wasm_alloc`
+///
+/// # Safety
+/// `ptr` and `len` must come from a single `wasm_alloc` call and not have been taken before.
+pub(crate) unsafe fn take_bytes(ptr: *mut u8, len: usize) -> Vec<u8> {
+    Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)).into_vec()
+}
+
+/// Take ownership of a UTF-8 string buffer created by `wasm_alloc`
+///
+/// # Safety
+/// Same requirements as `take_bytes`.
+pub(crate) unsafe fn take_string(ptr: *mut u8, len: usize) -> String {
+    String::from_utf8_lossy(&take_bytes(ptr, len)).into_owned()
+}
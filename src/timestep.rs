@@ -0,0 +1,136 @@
+use crate::error::Error;
+
+/// Settings of a fixed-step update loop. Times are in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedTimestep {
+    /// Time simulated by each update step
+    pub step: f32,
+    /// Most update steps run for a single animation frame. Time left over beyond them is dropped,
+    /// so slow updates cannot fall further and further behind
+    pub max_steps: u32,
+    /// Longest frame time taken into account, e.g. after the page was hidden or a long stall
+    pub max_frame_time: f32,
+}
+
+impl FixedTimestep {
+    /// Update `rate` times per second, failing unless `rate` is finite and positive
+    pub fn from_rate(rate: f32) -> Result<FixedTimestep, Error> {
+        let timestep = FixedTimestep { step: 1.0 / rate, ..FixedTimestep::default() };
+        timestep.check()?;
+        Ok(timestep)
+    }
+
+    /// Fail unless `step` is finite and positive, `max_steps` is not 0 and `max_frame_time` is
+    /// not negative or NaN
+    pub fn check(&self) -> Result<(), Error> {
+        if self.step.is_finite() && self.step > 0.0 && self.max_steps > 0 && self.max_frame_time >= 0.0 {
+            return Ok(());
+        }
+        Err(Error::InvalidTimestep(format!(
+            "step {}, max_steps {} and max_frame_time {}", self.step, self.max_steps, self.max_frame_time
+        )))
+    }
+}
+
+impl Default for FixedTimestep {
+    /// 120 steps per second
+    fn default() -> Self { FixedTimestep { step: 1.0 / 120.0, max_steps: 8, max_frame_time: 0.25 } }
+}
+
+/// Turns variable frame times into a number of fixed update steps.
+///
+/// Time that does not make a whole step is carried over to the next frame. `alpha` tells how far
+/// the current time is between the last two steps, to interpolate what gets drawn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accumulator {
+    timestep: FixedTimestep,
+    time: f32,
+}
+
+impl Accumulator {
+    /// Fails when `timestep` does not pass `FixedTimestep::check`
+    pub fn new(timestep: FixedTimestep) -> Result<Accumulator, Error> {
+        timestep.check()?;
+        Ok(Accumulator { timestep, time: 0.0 })
+    }
+
+    pub fn timestep(&self) -> &FixedTimestep { &self.timestep }
+
+    /// Add the time elapsed since the previous frame and return the number of steps to run
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.time += elapsed.clamp(0.0, self.timestep.max_frame_time);
+
+        let available = (self.time / self.timestep.step).floor() as u32;
+        let steps = available.min(self.timestep.max_steps);
+        self.time -= steps as f32 * self.timestep.step;
+        if available > steps {
+            // Spiral of death: drop the backlog rather than try to catch up on it
+            self.time %= self.timestep.step;
+        }
+        steps
+    }
+
+    /// Fraction of a step accumulated since the last step, in [0, 1)
+    pub fn alpha(&self) -> f32 { (self.time / self.timestep.step).clamp(0.0, 1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Binary fractions, so the sums are exact
+    fn accumulator(max_steps: u32, max_frame_time: f32) -> Accumulator {
+        Accumulator::new(FixedTimestep { step: 0.25, max_steps, max_frame_time }).unwrap()
+    }
+
+    #[test]
+    fn invalid_settings_are_refused() {
+        for rate in [0.0, -60.0, f32::NAN, f32::INFINITY] {
+            assert!(matches!(FixedTimestep::from_rate(rate), Err(Error::InvalidTimestep(_))), "rate {rate}");
+        }
+        assert_eq!(FixedTimestep::from_rate(120.0), Ok(FixedTimestep::default()));
+
+        let valid = FixedTimestep::default();
+        for timestep in [
+            FixedTimestep { step: 0.0, ..valid },
+            FixedTimestep { step: f32::NAN, ..valid },
+            FixedTimestep { max_steps: 0, ..valid },
+            FixedTimestep { max_frame_time: -1.0, ..valid },
+            FixedTimestep { max_frame_time: f32::NAN, ..valid },
+        ] {
+            assert!(Accumulator::new(timestep).is_err(), "{timestep:?}");
+        }
+    }
+
+    #[test]
+    fn partial_steps_carry_over() {
+        let mut accumulator = accumulator(8, 10.0);
+        assert_eq!(accumulator.advance(0.125), 0);
+        assert_eq!(accumulator.alpha(), 0.5);
+        assert_eq!(accumulator.advance(0.5), 2);
+        assert_eq!(accumulator.alpha(), 0.5);
+        assert_eq!(accumulator.advance(0.125), 1);
+        assert_eq!(accumulator.alpha(), 0.0);
+    }
+
+    #[test]
+    fn steps_are_capped_and_backlog_dropped() {
+        let mut accumulator = accumulator(2, 10.0);
+        assert_eq!(accumulator.advance(1.125), 2);
+        // The three steps left over are dropped, the partial step is kept
+        assert_eq!(accumulator.alpha(), 0.5);
+        assert_eq!(accumulator.advance(0.125), 1);
+    }
+
+    #[test]
+    fn frame_time_is_clamped() {
+        let mut accumulator = accumulator(8, 0.5);
+        assert_eq!(accumulator.advance(60.0), 2);
+        assert_eq!(accumulator.alpha(), 0.0);
+
+        // Negative elapsed time, e.g. from clock adjustments, does not go back
+        assert_eq!(accumulator.advance(0.125), 0);
+        assert_eq!(accumulator.advance(-1.0), 0);
+        assert_eq!(accumulator.alpha(), 0.5);
+    }
+}
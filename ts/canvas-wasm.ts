@@ -1,143 +1,289 @@
 // Library for WASM canvas glue
 
-import { decodeWasmString, encodeWasmString } from './wasm-utils.js';
+import { decodeWasmString, encodeWasmBytes, encodeWasmString } from './wasm-utils.js';
 import { getWasmExports, WasmExports } from './wasm-utils.js';
 
 
+// x, y, movementX, movementY, button, buttons, modifiers
+type MouseEventArgs = [number, number, number, number, number, number, number];
+
+// pointerId, pointerType, isPrimary, x, y, pressure, tiltX, tiltY, width, height, button, buttons, modifiers
+type PointerEventArgs = [number, number, number, number, number, number, number, number, number, number, number, number, number];
+
 interface CanvasExports extends WasmExports {
-    on_mouse_move(canvasId: number, x: number, y: number): void;
-    on_mouse_down(canvasId: number, x: number, y: number, button: number): void;
-    on_mouse_up(canvasId: number, x: number, y: number, button: number): void;
-    on_double_click(canvasId: number, x: number, y: number, button: number): void;
-    on_wheel(canvasId: number, x: number, y: number, deltaY: number): void;
+    on_mouse_move(canvasId: number, ...event: MouseEventArgs): void;
+    on_mouse_down(canvasId: number, ...event: MouseEventArgs): void;
+    on_mouse_up(canvasId: number, ...event: MouseEventArgs): void;
+    on_double_click(canvasId: number, ...event: MouseEventArgs): void;
+    on_mouse_enter(canvasId: number, ...event: MouseEventArgs): void;
+    on_mouse_leave(canvasId: number, ...event: MouseEventArgs): void;
+    on_pointer_down(canvasId: number, ...event: PointerEventArgs): void;
+    on_pointer_move(canvasId: number, ...event: PointerEventArgs): void;
+    on_pointer_up(canvasId: number, ...event: PointerEventArgs): void;
+    on_pointer_cancel(canvasId: number, ...event: PointerEventArgs): void;
+    on_wheel(canvasId: number, x: number, y: number, deltaX: number, deltaY: number, deltaZ: number,
+             modifiers: number, isPinch: number): void;
     on_animation_frame(canvasId: number, elapsed: number): void;
-    on_key_down(canvasId: number, keyCode: number): void;
-    on_key_up(canvasId: number, keyCode: number): void;
+    on_key_down(canvasId: number, key: number, code: number, modifiers: number, repeat: number, location: number): void;
+    on_key_up(canvasId: number, key: number, code: number, modifiers: number, repeat: number, location: number): void;
+    on_resize(canvasId: number, width: number, height: number): void;
+    on_focus(canvasId: number): void;
+    on_blur(canvasId: number): void;
+    on_visibility_change(canvasId: number, visible: number): void;
+    on_pointer_lock_change(canvasId: number, locked: number): void;
+    on_fullscreen_change(canvasId: number, fullscreen: number): void;
+    on_text_input(canvasId: number, textPtr: number, textLen: number): void;
+    on_text_composition(canvasId: number, textPtr: number, textLen: number): void;
+    on_file_dropped(canvasId: number, namePtr: number, nameLen: number, mimePtr: number, mimeLen: number,
+                    dataPtr: number, dataLen: number, x: number, y: number): void;
+    on_file_error(canvasId: number, namePtr: number, nameLen: number, error: number, size: number): void;
 }
 
 interface CanvasInfo {
     canvas: HTMLCanvasElement;
     context: CanvasRenderingContext2D;
-    animationId: number | null; // Animation loop id for this canvas
-    timer: number | null; // Timer for this canvas
+    animationId: number | null; // Pending animation frame for this canvas
+    timer: number | null; // Time of the last frame reported to WASM
+    loopActive: boolean; // Animation loop started, even if idle in on-demand mode
+    targetFps: number; // Highest frame rate reported to WASM, 0 for no limit
+    onDemand: boolean; // Only draw frames after request_redraw or input events
+    redrawRequested: boolean; // Redraw requested while a frame was pending or being drawn
+    textInput: HTMLTextAreaElement; // Hidden field receiving keyboard input while text input is active
+    textInputActive: boolean;
+    wheelRequiresFocus: boolean; // Only capture wheel events while the canvas is focused
+    hidpi: boolean; // Backing store follows the CSS size times devicePixelRatio
+    pixelRatio: number; // Backing store pixels per canvas unit
+    pauseWhenHidden: boolean; // Stop the animation loop while the page is hidden
+    pausedByVisibility: boolean; // Animation loop stopped because the page got hidden
+    pointerLocked: boolean; // Canvas holds the pointer lock
+    fullscreen: boolean;
+    windowedSize: [number, number] | null; // Size to restore when leaving fullscreen
+    maxFileSize: number; // Largest file read into WASM memory, in bytes
+    created: boolean; // Element made by create_canvas, removed on destroy
+    abort: AbortController; // Removes every event listener on destroy
+    resizeObserver: ResizeObserver | null;
+    contextLost: boolean; // The browser dropped the 2D context, e.g. under GPU memory pressure
 }
 
 const CANVAS_REGISTRY: Map<number, CanvasInfo> = new Map();
 
+// Status codes returned to WASM, matching the Rust side
+const STATUS_OK = 0;
+const STATUS_MISSING_ELEMENT = 1;
+const STATUS_NOT_A_CANVAS = 2;
+const STATUS_UNKNOWN_CANVAS = 3;
+const STATUS_CONTEXT_LOST = 4;
+const STATUS_CONTEXT_UNAVAILABLE = 5; // The element already has another kind of context, e.g. WebGL
+
 export function getCanvasImports() {
     return { Canvas: {
-        register_canvas(namePtr: number, nameLen: number, canvasId: number) {
+        register_canvas(namePtr: number, nameLen: number, canvasId: number): number {
             const name = decodeWasmString(namePtr, nameLen);
-            const canvas = document.getElementById(name)! as HTMLCanvasElement;
-            const context = canvas.getContext('2d')! as CanvasRenderingContext2D;
-            CANVAS_REGISTRY.set(canvasId, { canvas, context, animationId: null, timer: null });
-            canvas.addEventListener('mousemove', (event) => {
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_mouse_move(canvasId, event.offsetX, event.offsetY);
-            });
+            const element = document.getElementById(name);
+            if (element === null) { return STATUS_MISSING_ELEMENT; }
+            if (!(element instanceof HTMLCanvasElement)) { return STATUS_NOT_A_CANVAS; }
+            return registerCanvas(element, canvasId, false);
+        },
 
+        create_canvas: (parentPtr: number, parentLen: number, canvasId: number, width: number, height: number): number => {
+            const parent = document.getElementById(decodeWasmString(parentPtr, parentLen));
+            if (parent === null) { return STATUS_MISSING_ELEMENT; }
+            const canvas = document.createElement('canvas');
+            canvas.width = width;
+            canvas.height = height;
+            parent.appendChild(canvas);
+            // Registered by reference, the page may have elements with any id
+            const status = registerCanvas(canvas, canvasId, true);
+            if (status !== STATUS_OK) { canvas.remove(); }
+            return status;
+        },
+        status: (canvasId: number): number => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return STATUS_UNKNOWN_CANVAS; }
+            return canvasInfo.contextLost ? STATUS_CONTEXT_LOST : STATUS_OK;
+        },
+        destroy_canvas: (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            stopAnimationLoop(canvasInfo);
+            canvasInfo.abort.abort();
+            canvasInfo.resizeObserver?.disconnect();
+            if (document.pointerLockElement === canvasInfo.canvas) { document.exitPointerLock(); }
+            if (document.fullscreenElement === canvasInfo.canvas) { document.exitFullscreen(); }
+            canvasInfo.textInput.remove();
+            if (canvasInfo.created) { canvasInfo.canvas.remove(); }
+            CANVAS_REGISTRY.delete(canvasId);
+        },
 
-            // Ensure canvas is focusable for keyboard events
-            canvas.tabIndex = 0;
+        // --- Animation Loop ---
+        start_animation_loop:  (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (canvasInfo) { startAnimationLoop(canvasId, canvasInfo); }
+        },
+        stop_animation_loop:   (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            canvasInfo.pausedByVisibility = false;
+            stopAnimationLoop(canvasInfo);
+        },
+        set_pause_when_hidden: (canvasId: number, pause: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.pauseWhenHidden = pause !== 0; }
+        },
+        set_target_fps: (canvasId: number, fps: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.targetFps = fps; }
+        },
+        set_redraw_on_demand: (canvasId: number, onDemand: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            canvasInfo.onDemand = onDemand !== 0;
+            if (!canvasInfo.onDemand) { requestRedraw(canvasId, canvasInfo); } // Resume continuous frames
+        },
+        request_redraw: (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (canvasInfo) { requestRedraw(canvasId, canvasInfo); }
+        },
 
-            canvas.addEventListener('mousedown', (event) => {
-                // Focus the canvas when clicked to enable keyboard events
-                canvas.focus();
-                // Only prevent default for middle mouse button (button 1) to stop scrolling
-                // Allow left and right clicks to focus the canvas normally
-                if (event.button === 1) { event.preventDefault(); }
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_mouse_down(canvasId, event.offsetX, event.offsetY, event.button);
-            });
-            canvas.addEventListener('mouseup', (event) => {
-                // Only prevent default for middle mouse button
-                if (event.button === 1) { event.preventDefault(); }
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_mouse_up(canvasId, event.offsetX, event.offsetY, event.button);
-            });
-            canvas.addEventListener('contextmenu', (event) => {
-                event.preventDefault(); // Prevent right-click context menu
-            });
-            canvas.addEventListener('dblclick', (event) => {
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_double_click(canvasId, event.offsetX, event.offsetY, event.button);
-            });
-            canvas.addEventListener('wheel', (event) => {
-                // Only handle wheel events when canvas is focused (actively clicked on)
-                if (document.activeElement !== canvas) { return; }
-                event.preventDefault(); // Prevent page scroll
-                event.stopPropagation(); // Stop event bubbling
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_wheel(canvasId, event.offsetX, event.offsetY, event.deltaY);
-            }, { passive: false }); // Explicitly set passive: false to allow preventDefault
-
-            canvas.addEventListener('keydown', (event) => {
-                // Only handle keydown if canvas is focused
-                if (document.activeElement !== canvas) { return; }
-                event.preventDefault(); // Prevent default browser behavior
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_key_down(canvasId, getKeyCode(event.key));
-            });
-            canvas.addEventListener('keyup', (event) => {
-                // Only handle keyup if canvas is focused
-                if (event.target !== canvas) { return; }
-                event.preventDefault(); // Prevent default browser behavior
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_key_up(canvasId, getKeyCode(event.key));
-            });
+        // --- Text Input ---
+        start_text_input: (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            canvasInfo.textInputActive = true;
+            // Keep the hidden field over the canvas so IME candidate windows show up next to it
+            canvasInfo.textInput.style.left = `${canvasInfo.canvas.offsetLeft}px`;
+            canvasInfo.textInput.style.top = `${canvasInfo.canvas.offsetTop}px`;
+            if (document.activeElement === canvasInfo.canvas) { canvasInfo.textInput.focus({ preventScroll: true }); }
+        },
+        stop_text_input: (canvasId: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            canvasInfo.textInputActive = false;
+            if (document.activeElement === canvasInfo.textInput) { canvasInfo.canvas.focus({ preventScroll: true }); }
+        },
 
-            // Auto-focus canvas when clicked to enable keyboard events
-            canvas.addEventListener('click', () => {
-                canvas.focus();
+        // --- Files ---
+        set_max_file_size: (canvasId: number, bytes: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.maxFileSize = bytes; }
+        },
+        open_file_dialog: (canvasId: number, acceptPtr: number, acceptLen: number, multiple: number) => {
+            const canvasInfo = CANVAS_REGISTRY.get(canvasId);
+            if (!canvasInfo) { return; }
+            const input = document.createElement('input');
+            input.type = 'file';
+            input.accept = decodeWasmString(acceptPtr, acceptLen);
+            input.multiple = multiple !== 0;
+            input.addEventListener('change', () => {
+                for (const file of Array.from(input.files ?? [])) {
+                    readFile(canvasId, canvasInfo, file, NaN, NaN);
+                }
             });
+            input.click();
         },
 
-        // --- Animation Loop ---
-        start_animation_loop:  (canvasId: number) => {
-            const canvasInfo = CANVAS_REGISTRY.get(canvasId)!;
-            if (canvasInfo.animationId !== null) return; // Already running
-
-            function animationFrame() {
-                let currTime = performance.now();
-                let elapsed = currTime - (canvasInfo.timer || currTime);
-                canvasInfo.timer = currTime;
+        // --- Wheel ---
+        set_wheel_requires_focus: (canvasId: number, requiresFocus: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.wheelRequiresFocus = requiresFocus !== 0; }
+        },
 
-                let expo = getWasmExports() as CanvasExports;
-                expo.on_animation_frame(canvasId, elapsed / 1000.0); // Convert to seconds
-                canvasInfo.animationId = requestAnimationFrame(animationFrame);
-            }
+        // --- Cursor & Pointer Lock ---
+        set_cursor: (canvasId: number, cssPtr: number, cssLen: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.canvas.style.cursor = decodeWasmString(cssPtr, cssLen); }
+        },
+        request_pointer_lock: (canvasId: number) => {
+            // Newer browsers return a promise, rejected without a user gesture. The outcome is
+            // reported through pointerlockchange either way.
+            const request = CANVAS_REGISTRY.get(canvasId)?.canvas.requestPointerLock() as unknown as Promise<void> | undefined;
+            request?.catch(() => {});
+        },
+        exit_pointer_lock: (canvasId: number) => {
+            if (document.pointerLockElement === CANVAS_REGISTRY.get(canvasId)?.canvas) { document.exitPointerLock(); }
+        },
+        is_pointer_locked: (canvasId: number): boolean => {
+            return document.pointerLockElement === CANVAS_REGISTRY.get(canvasId)?.canvas;
+        },
 
-            canvasInfo.timer = performance.now() - 16; // Start timer with a small offset for 60Hz
-            canvasInfo.animationId = requestAnimationFrame(animationFrame);
+        // --- Fullscreen ---
+        request_fullscreen: (canvasId: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (!info) { return; }
+            if (document.fullscreenElement === info.canvas) { return; }
+            info.windowedSize = [info.canvas.width / info.pixelRatio, info.canvas.height / info.pixelRatio];
+            // Rejected without a user gesture, in which case there is nothing to restore
+            info.canvas.requestFullscreen().catch(() => { info.windowedSize = null; });
         },
-        stop_animation_loop:   (canvasId: number) => {
-            const canvasInfo = CANVAS_REGISTRY.get(canvasId)!;
-            if (canvasInfo.animationId !== null) {
-                cancelAnimationFrame(canvasInfo.animationId);
-                canvasInfo.animationId = null;
-            }
+        exit_fullscreen: (canvasId: number) => {
+            if (document.fullscreenElement === CANVAS_REGISTRY.get(canvasId)?.canvas) { document.exitFullscreen(); }
+        },
+        is_fullscreen: (canvasId: number): boolean => {
+            return document.fullscreenElement === CANVAS_REGISTRY.get(canvasId)?.canvas;
         },
 
         // --- Canvas Dimensions ---
-        height:     (canvasId: number): number         => { return CANVAS_REGISTRY.get(canvasId)!.canvas.height;   },
-        width:      (canvasId: number): number         => { return CANVAS_REGISTRY.get(canvasId)!.canvas.width;    },
-        set_height: (canvasId: number, height: number) => { CANVAS_REGISTRY.get(canvasId)!.canvas.height = height; },
-        set_width:  (canvasId: number, width: number)  => { CANVAS_REGISTRY.get(canvasId)!.canvas.width = width;   },
+        // Sizes are in canvas units, i.e. CSS pixels in HiDPI mode
+        height:     (canvasId: number): number => { const info = CANVAS_REGISTRY.get(canvasId); return info ? info.canvas.height / info.pixelRatio : 0; },
+        width:      (canvasId: number): number => { const info = CANVAS_REGISTRY.get(canvasId); return info ? info.canvas.width / info.pixelRatio : 0;  },
+        set_height: (canvasId: number, height: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (!info) { return; }
+            if (!info.hidpi) { info.canvas.height = height; return; }
+            info.canvas.style.height = `${height}px`;
+            resizeBackingStore(info, info.canvas.width / info.pixelRatio, height);
+        },
+        set_width: (canvasId: number, width: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (!info) { return; }
+            if (!info.hidpi) { info.canvas.width = width; return; }
+            info.canvas.style.width = `${width}px`;
+            resizeBackingStore(info, width, info.canvas.height / info.pixelRatio);
+        },
+        set_hidpi: (canvasId: number, enabled: number) => {
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (!info) { return; }
+            const width = info.canvas.width / info.pixelRatio;
+            const height = info.canvas.height / info.pixelRatio;
+            info.hidpi = enabled !== 0;
+            if (info.hidpi) {
+                // When the page does not size the canvas with CSS, its size comes from the backing
+                // store. Keep the current size so growing the backing store does not grow the canvas.
+                const style = getComputedStyle(info.canvas);
+                if (style.width === `${info.canvas.width}px`)   { info.canvas.style.width = `${width}px`; }
+                if (style.height === `${info.canvas.height}px`) { info.canvas.style.height = `${height}px`; }
+            }
+            resizeBackingStore(info, width, height);
+        },
+        pixel_ratio: (canvasId: number): number => CANVAS_REGISTRY.get(canvasId)?.pixelRatio ?? 1,
 
         // --- Font & Text ---
+        font_loaded: (fontPtr: number, fontLen: number): boolean => {
+            const font = decodeWasmString(fontPtr, fontLen);
+            try {
+                if (document.fonts?.check(font) ?? true) { return true; }
+            } catch {
+                return true; // Invalid font strings are ignored by the context, not waited for
+            }
+            // Fonts only start loading once something uses them, so request it for the next calls
+            document.fonts.load(font).catch(() => {});
+            return false;
+        },
         font: (canvasId: number) => {
-            return encodeWasmString(CANVAS_REGISTRY.get(canvasId)!.context.font);
+            return encodeWasmString(CANVAS_REGISTRY.get(canvasId)?.context.font ?? "");
         },
         set_font: (canvasId: number, fontPtr: number, fontLen: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.font = `${decodeWasmString(fontPtr, fontLen)}`;
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.context.font = `${decodeWasmString(fontPtr, fontLen)}`; }
         },
         fill_text: (canvasId: number, textPtr: number, textLen: number, x: number, y: number) => {
             const text = decodeWasmString(textPtr, textLen);
-            CANVAS_REGISTRY.get(canvasId)!.context.fillText(text, x, y);
+            CANVAS_REGISTRY.get(canvasId)?.context.fillText(text, x, y);
         },
         measure_text_width: (canvasId: number, textPtr: number, textLen: number): number => {
             const text = decodeWasmString(textPtr, textLen);
-            const ctx = CANVAS_REGISTRY.get(canvasId)!.context;
+            const ctx = CANVAS_REGISTRY.get(canvasId)?.context;
+            if (!ctx) { return 0; }
             ctx.save();
             const width = ctx.measureText(text).width;
             ctx.restore();
@@ -146,74 +292,585 @@ export function getCanvasImports() {
 
         // --- Drawing Primitives ---
         arc: (canvasId: number, x: number, y: number, radius: number, startAngle: number, endAngle: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.arc(x, y, radius, startAngle, endAngle);
+            CANVAS_REGISTRY.get(canvasId)?.context.arc(x, y, radius, startAngle, endAngle);
         },
         begin_path: (canvasId: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.beginPath();
+            CANVAS_REGISTRY.get(canvasId)?.context.beginPath();
         },
         clear_rect: (canvasId: number, x: number, y: number, width: number, height: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.clearRect(x, y, width, height);
+            CANVAS_REGISTRY.get(canvasId)?.context.clearRect(x, y, width, height);
         },
         fill: (canvasId: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.fill();
+            CANVAS_REGISTRY.get(canvasId)?.context.fill();
         },
         fill_rect: (canvasId: number, x: number, y: number, width: number, height: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.fillRect(x, y, width, height);
+            CANVAS_REGISTRY.get(canvasId)?.context.fillRect(x, y, width, height);
         },
         line_to: (canvasId: number, x: number, y: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.lineTo(x, y);
+            CANVAS_REGISTRY.get(canvasId)?.context.lineTo(x, y);
         },
         move_to: (canvasId: number, x: number, y: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.moveTo(x, y);
+            CANVAS_REGISTRY.get(canvasId)?.context.moveTo(x, y);
         },
         stroke: (canvasId: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.stroke();
+            CANVAS_REGISTRY.get(canvasId)?.context.stroke();
         },
         stroke_rect: (canvasId: number, x: number, y: number, width: number, height: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.strokeRect(x, y, width, height);
+            CANVAS_REGISTRY.get(canvasId)?.context.strokeRect(x, y, width, height);
         },
 
         // --- Color & Styling ---
         set_fill_color: (canvasId: number, r: number, g: number, b: number, a: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.fillStyle = `rgba(${r}, ${g}, ${b}, ${a})`;
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.context.fillStyle = `rgba(${r}, ${g}, ${b}, ${a})`; }
         },
         set_line_width: (canvasId: number, width: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.lineWidth = width;
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.context.lineWidth = width; }
         },
         set_stroke_color: (canvasId: number, r: number, g: number, b: number, a: number) => {
-            CANVAS_REGISTRY.get(canvasId)!.context.strokeStyle = `rgba(${r}, ${g}, ${b}, ${a})`;
+            const info = CANVAS_REGISTRY.get(canvasId);
+            if (info) { info.context.strokeStyle = `rgba(${r}, ${g}, ${b}, ${a})`; }
         },
     }};
 }
 
 
+// --- Animation Loop ---
+const FRAME_TIME = 16; // Elapsed time reported for the first frame, in ms, as for 60Hz
+
+const FPS_TOLERANCE = 1; // Frames this early, in ms, still count as on time with a target FPS
+
+// Events that trigger a redraw in on-demand mode
+const REDRAW_EVENTS = ['pointerdown', 'pointermove', 'pointerup', 'pointercancel', 'mouseenter', 'mouseleave',
+                       'dblclick', 'wheel', 'keydown', 'keyup', 'focus', 'blur', 'input', 'compositionupdate', 'drop'];
+
+// Elapsed time reported for the first frame after the loop starts or wakes up
+function frameInterval(canvasInfo: CanvasInfo): number {
+    return canvasInfo.targetFps > 0 ? 1000 / canvasInfo.targetFps : FRAME_TIME;
+}
+
+// Attach event listeners to a canvas element and track it under `canvasId`. `created` elements
+// belong to WASM and are removed when the canvas is destroyed
+function registerCanvas(canvas: HTMLCanvasElement, canvasId: number, created: boolean): number {
+    const context = canvas.getContext('2d');
+    if (context === null) { return STATUS_CONTEXT_UNAVAILABLE; }
+    const textInput = createTextInput(canvas);
+    const abort = new AbortController();
+    const signal = abort.signal;
+    const canvasInfo: CanvasInfo = {
+        canvas, context, animationId: null, timer: null,
+        loopActive: false, targetFps: 0, onDemand: false, redrawRequested: false,
+        textInput, textInputActive: false,
+        wheelRequiresFocus: true,
+        hidpi: false, pixelRatio: 1,
+        pauseWhenHidden: false, pausedByVisibility: false,
+        pointerLocked: false,
+        fullscreen: false, windowedSize: null,
+        maxFileSize: DEFAULT_MAX_FILE_SIZE,
+        created, abort, resizeObserver: null,
+        contextLost: false,
+    };
+    CANVAS_REGISTRY.set(canvasId, canvasInfo);
+    canvas.addEventListener('mousemove', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_mouse_move(canvasId, ...getMouseEventArgs(event, NO_BUTTON));
+    }, { signal });
+    canvas.addEventListener('mouseenter', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_mouse_enter(canvasId, ...getMouseEventArgs(event, NO_BUTTON));
+    }, { signal });
+    canvas.addEventListener('mouseleave', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_mouse_leave(canvasId, ...getMouseEventArgs(event, NO_BUTTON));
+    }, { signal });
+
+
+    // Ensure canvas is focusable for keyboard events
+    canvas.tabIndex = 0;
+
+    canvas.addEventListener('mousedown', (event) => {
+        // Focus the canvas when clicked to enable keyboard events
+        focusCanvas(canvasInfo);
+        // Only prevent default for middle mouse button (button 1) to stop scrolling
+        // Allow left and right clicks to focus the canvas normally
+        if (event.button === 1) { event.preventDefault(); }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_mouse_down(canvasId, ...getMouseEventArgs(event, event.button));
+    }, { signal });
+    canvas.addEventListener('mouseup', (event) => {
+        // Only prevent default for middle mouse button
+        if (event.button === 1) { event.preventDefault(); }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_mouse_up(canvasId, ...getMouseEventArgs(event, event.button));
+    }, { signal });
+
+    // Report changes of the displayed size, resizing the backing store in HiDPI mode and
+    // while fullscreen
+    const resizeObserver = new ResizeObserver((entries) => {
+        const { width, height } = entries[entries.length - 1].contentRect;
+        if (canvasInfo.hidpi || document.fullscreenElement === canvas) {
+            resizeBackingStore(canvasInfo, width, height);
+        }
+        if (canvasInfo.onDemand) { requestRedraw(canvasId, canvasInfo); }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_resize(canvasId, width, height);
+    });
+    resizeObserver.observe(canvas);
+    canvasInfo.resizeObserver = resizeObserver;
+
+    // Pointer lock changes are reported on the document, keep those concerning this canvas
+    document.addEventListener('pointerlockchange', () => {
+        const locked = document.pointerLockElement === canvas;
+        if (locked === canvasInfo.pointerLocked) { return; }
+        canvasInfo.pointerLocked = locked;
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_pointer_lock_change(canvasId, locked ? 1 : 0);
+    }, { signal });
+
+    // Restore the windowed size when leaving fullscreen, the resize observer reports it
+    document.addEventListener('fullscreenchange', () => {
+        const fullscreen = document.fullscreenElement === canvas;
+        if (fullscreen === canvasInfo.fullscreen) { return; }
+        canvasInfo.fullscreen = fullscreen;
+        if (!fullscreen && canvasInfo.windowedSize !== null) {
+            resizeBackingStore(canvasInfo, ...canvasInfo.windowedSize);
+            canvasInfo.windowedSize = null;
+        }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_fullscreen_change(canvasId, fullscreen ? 1 : 0);
+    }, { signal });
+
+    // Pointer events cover pen and touch input. Disable touch panning and zooming on the
+    // canvas so touches are delivered as pointer events instead of scrolling the page.
+    canvas.style.touchAction = 'none';
+    canvas.addEventListener('pointerdown', (event) => {
+        // Capture the pointer so drags keep reporting events outside of the canvas
+        canvas.setPointerCapture(event.pointerId);
+        if (event.pointerType !== 'mouse') { focusCanvas(canvasInfo); }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_pointer_down(canvasId, ...getPointerEventArgs(event));
+    }, { signal });
+    canvas.addEventListener('pointermove', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_pointer_move(canvasId, ...getPointerEventArgs(event));
+    }, { signal });
+    canvas.addEventListener('pointerup', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_pointer_up(canvasId, ...getPointerEventArgs(event));
+    }, { signal });
+    canvas.addEventListener('pointercancel', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_pointer_cancel(canvasId, ...getPointerEventArgs(event));
+    }, { signal });
+
+    canvas.addEventListener('contextmenu', (event) => {
+        event.preventDefault(); // Prevent right-click context menu
+    }, { signal });
+    canvas.addEventListener('dblclick', (event) => {
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_double_click(canvasId, ...getMouseEventArgs(event, event.button));
+    }, { signal });
+    canvas.addEventListener('wheel', (event) => {
+        // By default only handle wheel events when canvas is focused (actively clicked on)
+        if (canvasInfo.wheelRequiresFocus && !hasFocus(canvasInfo)) { return; }
+        event.preventDefault(); // Prevent page scroll, and page zoom for pinch gestures
+        event.stopPropagation(); // Stop event bubbling
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_wheel(canvasId, event.offsetX, event.offsetY,
+                      getWheelDelta(event.deltaX, event.deltaMode, canvas.clientWidth),
+                      getWheelDelta(event.deltaY, event.deltaMode, canvas.clientHeight),
+                      getWheelDelta(event.deltaZ, event.deltaMode, canvas.clientHeight),
+                      getModifiers(event), event.ctrlKey ? 1 : 0);
+    }, { passive: false, signal }); // Explicitly set passive: false to allow preventDefault
+
+    const onKeyDown = (event: KeyboardEvent) => {
+        // Only handle keydown if canvas is focused
+        if (!hasFocus(canvasInfo)) { return; }
+        // Keys used by an IME to compose text are reported through composition events
+        if (event.isComposing || event.keyCode === 229) { return; }
+        // Let the hidden text field receive the key so it produces input events, except
+        // for Tab which would move the focus away
+        if (!canvasInfo.textInputActive || event.key === "Tab") { event.preventDefault(); }
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_key_down(canvasId, getKeyCode(event.key), getPhysicalKeyCode(event.code),
+                         getModifiers(event), event.repeat ? 1 : 0, event.location);
+
+        // Without the hidden text field, characters come straight from the key event
+        if (!canvasInfo.textInputActive && isPrintable(event)) {
+            sendText(canvasId, event.key, "on_text_input");
+        }
+    };
+    const onKeyUp = (event: KeyboardEvent) => {
+        // Only handle keyup if canvas is focused
+        if (event.target !== canvas && event.target !== textInput) { return; }
+        if (event.isComposing || event.keyCode === 229) { return; }
+        event.preventDefault(); // Prevent default browser behavior
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_key_up(canvasId, getKeyCode(event.key), getPhysicalKeyCode(event.code),
+                       getModifiers(event), event.repeat ? 1 : 0, event.location);
+    };
+    canvas.addEventListener('keydown', onKeyDown, { signal });
+    canvas.addEventListener('keyup', onKeyUp, { signal });
+    textInput.addEventListener('keydown', onKeyDown, { signal });
+    textInput.addEventListener('keyup', onKeyUp, { signal });
+
+    // Focus changes, where moving the focus between the canvas and its hidden text field does
+    // not count. Keys held while the focus leaves are never released, WASM releases them.
+    canvas.addEventListener('focus', (event) => {
+        if (event.relatedTarget === textInput) { return; }
+        (getWasmExports() as CanvasExports).on_focus(canvasId);
+    }, { signal });
+    textInput.addEventListener('focus', (event) => {
+        if (event.relatedTarget === canvas) { return; }
+        (getWasmExports() as CanvasExports).on_focus(canvasId);
+    }, { signal });
+    canvas.addEventListener('blur', (event) => {
+        if (event.relatedTarget === textInput) { return; }
+        (getWasmExports() as CanvasExports).on_blur(canvasId);
+    }, { signal });
+    textInput.addEventListener('blur', (event) => {
+        if (event.relatedTarget === canvas) { return; }
+        (getWasmExports() as CanvasExports).on_blur(canvasId);
+    }, { signal });
+
+    // Text typed into the hidden field, with IME compositions reported while in progress
+    textInput.addEventListener('beforeinput', (event) => {
+        if (event.isComposing) { return; }
+        event.preventDefault(); // Keep the hidden field empty
+        if (event.inputType === "insertText" && event.data) { sendText(canvasId, event.data, "on_text_input"); }
+    }, { signal });
+    textInput.addEventListener('compositionupdate', (event) => {
+        sendText(canvasId, event.data, "on_text_composition");
+    }, { signal });
+    textInput.addEventListener('compositionend', (event) => {
+        sendText(canvasId, "", "on_text_composition");
+        if (event.data) { sendText(canvasId, event.data, "on_text_input"); }
+        textInput.value = "";
+    }, { signal });
+
+    // Files dropped on the canvas
+    canvas.addEventListener('dragenter', (event) => { event.preventDefault(); }, { signal });
+    canvas.addEventListener('dragover', (event) => {
+        event.preventDefault(); // Accept the drop
+        if (event.dataTransfer) { event.dataTransfer.dropEffect = 'copy'; }
+    }, { signal });
+    canvas.addEventListener('drop', (event) => {
+        event.preventDefault(); // Don't let the browser open the file
+        for (const file of Array.from(event.dataTransfer?.files ?? [])) {
+            readFile(canvasId, canvasInfo, file, event.offsetX, event.offsetY);
+        }
+    }, { signal });
+
+    // In on-demand mode, input may change what is drawn
+    for (const target of [canvas, textInput]) {
+        for (const type of REDRAW_EVENTS) {
+            target.addEventListener(type, () => {
+                if (canvasInfo.onDemand) { requestRedraw(canvasId, canvasInfo); }
+            }, { signal });
+        }
+    }
+
+    // Drawing is lost until the context is restored, reported through status
+    canvas.addEventListener('contextlost', () => { canvasInfo.contextLost = true; }, { signal });
+    canvas.addEventListener('contextrestored', () => { canvasInfo.contextLost = false; }, { signal });
+
+    // Auto-focus canvas when clicked to enable keyboard events
+    canvas.addEventListener('click', () => {
+        focusCanvas(canvasInfo);
+    }, { signal });
+    return STATUS_OK;
+}
+
+function startAnimationLoop(canvasId: number, canvasInfo: CanvasInfo) {
+    if (canvasInfo.loopActive) return; // Already running
+    canvasInfo.loopActive = true;
+    canvasInfo.timer = performance.now() - frameInterval(canvasInfo);
+    scheduleFrame(canvasId, canvasInfo);
+}
+
+function stopAnimationLoop(canvasInfo: CanvasInfo) {
+    canvasInfo.loopActive = false;
+    canvasInfo.redrawRequested = false;
+    if (canvasInfo.animationId !== null) {
+        cancelAnimationFrame(canvasInfo.animationId);
+        canvasInfo.animationId = null;
+    }
+}
+
+function scheduleFrame(canvasId: number, canvasInfo: CanvasInfo) {
+    if (!canvasInfo.loopActive || canvasInfo.animationId !== null) return;
+    canvasInfo.animationId = requestAnimationFrame(() => animationFrame(canvasId, canvasInfo));
+}
+
+function animationFrame(canvasId: number, canvasInfo: CanvasInfo) {
+    let currTime = performance.now();
+    let elapsed = currTime - (canvasInfo.timer || currTime);
+
+    // Too early for the target FPS, wait for a later display frame
+    if (canvasInfo.targetFps > 0 && elapsed < 1000 / canvasInfo.targetFps - FPS_TOLERANCE) {
+        canvasInfo.animationId = null;
+        scheduleFrame(canvasId, canvasInfo);
+        return;
+    }
+    canvasInfo.timer = currTime;
+    canvasInfo.redrawRequested = false;
+
+    // The frame stays pending while drawn, so redraws requested by WASM are only recorded
+    const frameId = canvasInfo.animationId;
+    let expo = getWasmExports() as CanvasExports;
+    expo.on_animation_frame(canvasId, elapsed / 1000.0); // Convert to seconds
+    // The loop was stopped, and maybe restarted with a frame of its own, while drawing
+    if (canvasInfo.animationId !== frameId) return;
+    canvasInfo.animationId = null;
+
+    if (!canvasInfo.onDemand || canvasInfo.redrawRequested) {
+        canvasInfo.redrawRequested = false;
+        scheduleFrame(canvasId, canvasInfo);
+    }
+}
+
+function requestRedraw(canvasId: number, canvasInfo: CanvasInfo) {
+    if (!canvasInfo.loopActive) return;
+    if (canvasInfo.animationId !== null) { canvasInfo.redrawRequested = true; return; }
+    // Waking up from idle, don't report the idle time as elapsed
+    canvasInfo.timer = performance.now() - frameInterval(canvasInfo);
+    scheduleFrame(canvasId, canvasInfo);
+}
+
+// Browsers stop animation frames while the page is hidden. Optionally stop the loops explicitly,
+// and make sure the time spent hidden is not reported as elapsed by the first frame after it.
+document.addEventListener('visibilitychange', () => {
+    const visible = document.visibilityState === 'visible';
+    for (const [canvasId, canvasInfo] of CANVAS_REGISTRY) {
+        if (!visible && canvasInfo.pauseWhenHidden && canvasInfo.loopActive) {
+            stopAnimationLoop(canvasInfo);
+            canvasInfo.pausedByVisibility = true;
+        } else if (visible && canvasInfo.pausedByVisibility) {
+            canvasInfo.pausedByVisibility = false;
+            startAnimationLoop(canvasId, canvasInfo);
+        } else if (visible && canvasInfo.loopActive) {
+            canvasInfo.timer = performance.now() - frameInterval(canvasInfo);
+        }
+
+        let expo = getWasmExports() as CanvasExports;
+        expo.on_visibility_change(canvasId, visible ? 1 : 0);
+    }
+});
+
+// devicePixelRatio changes when the window moves to a screen with another density or the page is
+// zoomed, without a CSS size change for the resize observer to report. The media query only
+// matches the ratio it was made with, so a new one is watched after each change.
+function watchPixelRatio() {
+    matchMedia(`(resolution: ${window.devicePixelRatio}dppx)`).addEventListener('change', () => {
+        for (const [canvasId, canvasInfo] of CANVAS_REGISTRY) {
+            if (!canvasInfo.hidpi || canvasInfo.pixelRatio === window.devicePixelRatio) { continue; }
+            resizeBackingStore(canvasInfo, canvasInfo.canvas.width / canvasInfo.pixelRatio, canvasInfo.canvas.height / canvasInfo.pixelRatio);
+            // Resizing cleared the canvas
+            if (canvasInfo.onDemand) { requestRedraw(canvasId, canvasInfo); }
+        }
+        watchPixelRatio();
+    }, { once: true });
+}
+watchPixelRatio();
+
+// Size the backing store for a canvas displayed at the given CSS size, and scale drawing so it
+// keeps using CSS pixels. Resizing the canvas resets its context, so the scale is always reapplied.
+function resizeBackingStore(info: CanvasInfo, cssWidth: number, cssHeight: number) {
+    info.pixelRatio = info.hidpi ? window.devicePixelRatio : 1;
+    const width = Math.round(cssWidth * info.pixelRatio);
+    const height = Math.round(cssHeight * info.pixelRatio);
+    if (info.canvas.width !== width)   { info.canvas.width = width; }
+    if (info.canvas.height !== height) { info.canvas.height = height; }
+    info.context.setTransform(info.pixelRatio, 0, 0, info.pixelRatio, 0, 0);
+}
+
+// Hidden text field placed next to the canvas, used to receive text and IME compositions
+function createTextInput(canvas: HTMLCanvasElement): HTMLTextAreaElement {
+    const textInput = document.createElement('textarea');
+    textInput.setAttribute('autocomplete', 'off');
+    textInput.setAttribute('autocapitalize', 'off');
+    textInput.setAttribute('aria-hidden', 'true');
+    textInput.spellcheck = false;
+    textInput.tabIndex = -1;
+    textInput.style.cssText = 'position: absolute; width: 1px; height: 1px; padding: 0; border: 0; '
+                            + 'opacity: 0; resize: none; overflow: hidden; pointer-events: none;';
+    canvas.insertAdjacentElement('afterend', textInput);
+    return textInput;
+}
+
+function hasFocus(canvasInfo: CanvasInfo): boolean {
+    return document.activeElement === canvasInfo.canvas || document.activeElement === canvasInfo.textInput;
+}
+
+function focusCanvas(canvasInfo: CanvasInfo) {
+    if (canvasInfo.textInputActive) {
+        canvasInfo.textInput.focus({ preventScroll: true });
+    } else {
+        canvasInfo.canvas.focus();
+    }
+}
+
+function sendText(canvasId: number, text: string, entry: "on_text_input" | "on_text_composition") {
+    const { ptr, len } = encodeWasmString(text);
+    (getWasmExports() as CanvasExports)[entry](canvasId, ptr, len);
+}
+
+// --- Files ---
+const DEFAULT_MAX_FILE_SIZE = 16 * 1024 * 1024;
+const FILE_TOO_LARGE = 1;
+const FILE_READ_FAILED = 2;
+
+// Read a file into WASM memory and hand it to the canvas, or report why it could not be
+function readFile(canvasId: number, canvasInfo: CanvasInfo, file: File, x: number, y: number) {
+    const reportError = (error: number) => {
+        const name = encodeWasmString(file.name);
+        (getWasmExports() as CanvasExports).on_file_error(canvasId, name.ptr, name.len, error, file.size);
+    };
+    if (file.size > canvasInfo.maxFileSize) { reportError(FILE_TOO_LARGE); return; }
+
+    file.arrayBuffer().then((buffer) => {
+        const name = encodeWasmString(file.name);
+        const mime = encodeWasmString(file.type);
+        const data = encodeWasmBytes(new Uint8Array(buffer));
+        (getWasmExports() as CanvasExports).on_file_dropped(canvasId, name.ptr, name.len, mime.ptr, mime.len,
+                                                            data.ptr, data.len, x, y);
+    }, () => reportError(FILE_READ_FAILED));
+}
+
+// Key events producing a single character, ignoring shortcuts with Ctrl or Meta. AltGr also sets
+// Ctrl on Windows, so characters like "@" or "€" typed with it are printable
+function isPrintable(event: KeyboardEvent): boolean {
+    if ([...event.key].length !== 1 || event.metaKey) { return false; }
+    if (!event.ctrlKey || event.getModifierState("AltGraph")) { return true; }
+    // Without the AltGraph state, Ctrl+Alt only counts as AltGr when it changed the character,
+    // so shortcuts like Ctrl+Alt+T are not typed
+    return event.altKey && event.key.toLowerCase() !== (unmodifiedKey(event.code) ?? event.key.toLowerCase());
+}
+
+// Character of a letter or digit key without modifiers, null for other keys
+function unmodifiedKey(code: string): string | null {
+    const match = /^(?:Key([A-Z])|Digit([0-9]))$/.exec(code);
+    if (match === null) { return null; }
+    return match[1] !== undefined ? match[1].toLowerCase() : match[2];
+}
+
+// Keys shared with the Rust `KeyCode` enum (values follow its legacy DOM keyCode numbering). A Rust
+// test reads this table and fails when its values and the enum drift apart.
+// Each entry lists the physical KeyboardEvent.code names and the logical KeyboardEvent.key names
+// that map to the value, so both lookups are built from the same table. Logical characters map to
+// main keyboard keys only: numpad keys report them with `KeyLocation::Numpad`, and shifted
+// punctuation other than "+" is left unknown rather than borrowing a numpad value.
+const UNKNOWN_KEY = 65535;
+const KEY_TABLE: [value: number, codes: string[], keys: string[]][] = [
+    [37,  ["ArrowLeft"],                   ["ArrowLeft"]],
+    [38,  ["ArrowUp"],                     ["ArrowUp"]],
+    [39,  ["ArrowRight"],                  ["ArrowRight"]],
+    [40,  ["ArrowDown"],                   ["ArrowDown"]],
+    [32,  ["Space"],                       [" "]],
+    [13,  ["Enter", "NumpadEnter"],        ["Enter"]],
+    [27,  ["Escape"],                      ["Escape"]],
+    [9,   ["Tab"],                         ["Tab"]],
+    [16,  ["ShiftLeft", "ShiftRight"],     ["Shift"]],
+    [17,  ["ControlLeft", "ControlRight"], ["Control"]],
+    [18,  ["AltLeft", "AltRight"],         ["Alt", "AltGraph"]],
+    [91,  ["MetaLeft", "MetaRight"],       ["Meta"]],
+    [20,  ["CapsLock"],                    ["CapsLock"]],
+    [93,  ["ContextMenu"],                 ["ContextMenu"]],
+    [8,   ["Backspace"],                   ["Backspace"]],
+    [46,  ["Delete"],                      ["Delete"]],
+    [45,  ["Insert"],                      ["Insert"]],
+    [36,  ["Home"],                        ["Home"]],
+    [35,  ["End"],                         ["End"]],
+    [33,  ["PageUp"],                      ["PageUp"]],
+    [34,  ["PageDown"],                    ["PageDown"]],
+    [19,  ["Pause"],                       ["Pause"]],
+    [44,  ["PrintScreen"],                 ["PrintScreen"]],
+    [144, ["NumLock"],                     ["NumLock"]],
+    [145, ["ScrollLock"],                  ["ScrollLock"]],
+    [106, ["NumpadMultiply"],              []],
+    [107, ["NumpadAdd"],                   []],
+    [109, ["NumpadSubtract"],              []],
+    [110, ["NumpadDecimal"],               []],
+    [111, ["NumpadDivide"],                []],
+    [189, ["Minus"],                       ["-"]],
+    [187, ["Equal"],                       ["=", "+"]],
+    [188, ["Comma"],                       [","]],
+    [190, ["Period"],                      ["."]],
+    [191, ["Slash"],                       ["/"]],
+    [186, ["Semicolon"],                   [";"]],
+    [222, ["Quote"],                       ["'"]],
+    [219, ["BracketLeft"],                 ["["]],
+    [221, ["BracketRight"],                ["]"]],
+    [220, ["Backslash"],                   ["\\"]],
+    [192, ["Backquote"],                   ["`"]],
+];
+for (let i = 0; i < 26; i++) {
+    const letter = String.fromCharCode(65 + i);
+    KEY_TABLE.push([65 + i, [`Key${letter}`], [letter, letter.toLowerCase()]]);
+}
+for (let i = 0; i < 10; i++) {
+    KEY_TABLE.push([48 + i, [`Digit${i}`], [`${i}`]]);
+    KEY_TABLE.push([96 + i, [`Numpad${i}`], []]);
+}
+for (let i = 1; i <= 12; i++) {
+    KEY_TABLE.push([111 + i, [`F${i}`], [`F${i}`]]);
+}
+
+const KEYS_BY_CODE: Map<string, number> = new Map();
+const KEYS_BY_NAME: Map<string, number> = new Map();
+for (const [value, codes, keys] of KEY_TABLE) {
+    codes.forEach((code) => KEYS_BY_CODE.set(code, value));
+    keys.forEach((key) => KEYS_BY_NAME.set(key, value));
+}
+
+// Logical key (KeyboardEvent.key), depends on layout and modifiers
 function getKeyCode(key: string): number {
-    switch (key) {
-        case "ArrowLeft":  return 37;
-        case "ArrowUp":    return 38;
-        case "ArrowRight": return 39;
-        case "ArrowDown":  return 40;
-        case "Escape":     return 27;
-        case "Enter":      return 13;
-        case "Tab":        return 9;
-        case "Backspace":  return 8;
-        case "Delete":     return 46;
-        case "Shift":      return 16;
-        case "Control":    return 17;
-        case "Alt":        return 18;
-        case "Meta":       return 91;
-        case "CapsLock":   return 20;
-        case " ":          return 32;
-        case "-":
-        case "Minus":      return 189;
-        case "+":
-        case "=":
-        case "Equal":      return 187;
-        default:
-            if (key.length !== 1) {
-                console.warn(`Unsupported key event: "${key}"`);
-                return 65535; // Return Unknown KeyCode value instead of 0
-            }
-            return key.toUpperCase().charCodeAt(0);
+    return KEYS_BY_NAME.get(key) ?? UNKNOWN_KEY;
+}
+
+// Physical key position (KeyboardEvent.code), independent of layout
+function getPhysicalKeyCode(code: string): number {
+    return KEYS_BY_CODE.get(code) ?? UNKNOWN_KEY;
+}
+
+// Button value reported for mouse events that are not caused by a button (MouseButton::Unknown)
+const NO_BUTTON = 255;
+
+function getMouseEventArgs(event: MouseEvent, button: number): MouseEventArgs {
+    return [event.offsetX, event.offsetY, event.movementX, event.movementY, button, event.buttons, getModifiers(event)];
+}
+
+function getPointerEventArgs(event: PointerEvent): PointerEventArgs {
+    return [
+        event.pointerId, getPointerType(event.pointerType), event.isPrimary ? 1 : 0,
+        event.offsetX, event.offsetY, event.pressure, event.tiltX, event.tiltY, event.width, event.height,
+        event.button < 0 ? NO_BUTTON : event.button, event.buttons, getModifiers(event),
+    ];
+}
+
+// Pointer types as in the Rust PointerType enum
+function getPointerType(pointerType: string): number {
+    switch (pointerType) {
+        case "mouse": return 0;
+        case "pen":   return 1;
+        case "touch": return 2;
+        default:      return 255;
+    }
+}
+
+// Wheel deltas converted to pixels, from lines or pages when the browser reports those
+const WHEEL_LINE_HEIGHT = 16;
+
+function getWheelDelta(delta: number, deltaMode: number, pageSize: number): number {
+    switch (deltaMode) {
+        case WheelEvent.DOM_DELTA_LINE: return delta * WHEEL_LINE_HEIGHT;
+        case WheelEvent.DOM_DELTA_PAGE: return delta * pageSize;
+        default:                        return delta;
     }
-}
\ No newline at end of file
+}
+
+// Modifier keys packed as bits: Shift = 1, Ctrl = 2, Alt = 4, Meta = 8
+function getModifiers(event: KeyboardEvent | MouseEvent): number {
+    return (event.shiftKey ? 1 : 0) | (event.ctrlKey ? 2 : 0) | (event.altKey ? 4 : 0) | (event.metaKey ? 8 : 0);
+}
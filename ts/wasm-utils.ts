@@ -10,6 +10,7 @@ export async function loadWasm(wasmPath: string, importObject?: WebAssembly.Impo
         {
             Browser: createBrowserImports(),
             Console: createConsoleImports(),
+            Gamepad: createGamepadImports(),
             ...importObject,
         })
         .then(result => {
@@ -35,14 +36,75 @@ function createConsoleImports() {
     return {
         log:   (ptr: number, len: number) => { console.log("[WASM]", decodeWasmString(ptr, len));   },
         error: (ptr: number, len: number) => { console.error("[WASM]", decodeWasmString(ptr, len)); },
+        warn:  (ptr: number, len: number) => { console.warn("[WASM]", decodeWasmString(ptr, len));  },
+        info:  (ptr: number, len: number) => { console.info("[WASM]", decodeWasmString(ptr, len));  },
+        debug: (ptr: number, len: number) => { console.debug("[WASM]", decodeWasmString(ptr, len)); },
     };
 }
 
+/// Timers and animation frames requested by WASM, by the id WASM gave them. Firing calls back
+/// into `on_timer` or `on_frame`
+const TIMERS: Map<number, number> = new Map();
+const FRAMES: Map<number, number> = new Map();
+
+type BrowserExports = WasmExports & {
+    on_timer(id: number): void;
+    on_frame(id: number, time: number): void;
+    run_tasks(): void;
+};
+
 function createBrowserImports() {
     return {
         alert:    (ptr: number, len: number) => { window.alert(decodeWasmString(ptr, len)); },
         time_now: (): number => performance.now(),
         random:   (): number => Math.random(),
+        set_timer: (id: number, ms: number, repeat: number) => {
+            const fire = () => {
+                if (!repeat) { TIMERS.delete(id); }
+                (getWasmExports() as BrowserExports).on_timer(id);
+            };
+            TIMERS.set(id, repeat ? window.setInterval(fire, ms) : window.setTimeout(fire, ms));
+        },
+        clear_timer: (id: number) => {
+            const handle = TIMERS.get(id);
+            if (handle === undefined) { return; }
+            // Timeouts and intervals share the same ids, either function clears both
+            window.clearTimeout(handle);
+            TIMERS.delete(id);
+        },
+        request_frame: (id: number) => {
+            FRAMES.set(id, requestAnimationFrame((time) => {
+                FRAMES.delete(id);
+                (getWasmExports() as BrowserExports).on_frame(id, time);
+            }));
+        },
+        cancel_frame: (id: number) => {
+            const handle = FRAMES.get(id);
+            if (handle === undefined) { return; }
+            cancelAnimationFrame(handle);
+            FRAMES.delete(id);
+        },
+        // Poll the async tasks woken since the last run, once the current event is handled
+        queue_microtask: () => {
+            queueMicrotask(() => (getWasmExports() as BrowserExports).run_tasks());
+        },
+    };
+}
+
+/// Gamepads are polled by WASM: `snapshot` reads the current state once per frame, and the
+/// other functions query that snapshot
+let GAMEPADS: (Gamepad | null)[] = [];
+
+function createGamepadImports() {
+    return {
+        snapshot:         (): number => { GAMEPADS = navigator.getGamepads ? navigator.getGamepads() : []; return GAMEPADS.length; },
+        connected:        (index: number): boolean => GAMEPADS[index]?.connected ?? false,
+        standard_mapping: (index: number): boolean => GAMEPADS[index]?.mapping === "standard",
+        button_count:     (index: number): number => GAMEPADS[index]?.buttons.length ?? 0,
+        button_value:     (index: number, button: number): number => GAMEPADS[index]?.buttons[button]?.value ?? 0,
+        button_pressed:   (index: number, button: number): boolean => GAMEPADS[index]?.buttons[button]?.pressed ?? false,
+        axis_count:       (index: number): number => GAMEPADS[index]?.axes.length ?? 0,
+        axis_value:       (index: number, axis: number): number => GAMEPADS[index]?.axes[axis] ?? 0,
     };
 }
 
@@ -54,11 +116,13 @@ export function decodeWasmString(ptr: number, len: number): string {
 }
 
 export function encodeWasmString(str: string): { ptr: number, len: number } {
-    const wasmExports = getWasmExports();
-    const encoder = new TextEncoder();
-    const bytes = encoder.encode(str);
-    const ptr = wasmExports.memory.grow(Math.ceil(bytes.length / 65536));
-    const memoryBuffer = new Uint8Array(wasmExports.memory.buffer);
-    memoryBuffer.set(bytes, ptr);
-    return { ptr, len: bytes.length };
+    return encodeWasmBytes(new TextEncoder().encode(str));
 }
+
+/// Copy bytes into a buffer allocated by WASM, which takes ownership when it receives the pointer
+export function encodeWasmBytes(bytes: Uint8Array): { ptr: number, len: number } {
+    const wasmExports = getWasmExports() as WasmExports & { wasm_alloc(len: number): number };
+    const ptr = wasmExports.wasm_alloc(bytes.length);
+    new Uint8Array(wasmExports.memory.buffer, ptr, bytes.length).set(bytes);
+    return { ptr, len: bytes.length };
+}
\ No newline at end of file